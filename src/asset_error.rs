@@ -0,0 +1,81 @@
+use luminance::tess::{TessError, TessMapError};
+use luminance::texture::TextureError;
+use std::fmt;
+use std::io;
+use wavefront_obj::ParseError;
+
+#[derive(Debug)]
+pub enum AssetError {
+    Io(io::Error),
+    Parse(ParseError),
+    EmptyMesh(String),
+    InvalidBakedMesh(String),
+    InvalidDetail(String),
+    InvalidImage(String),
+    TessBuild(TessError),
+    TessMap(TessMapError),
+    Texture(TextureError),
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::Io(e) => write!(f, "cannot read asset: {}", e),
+            AssetError::Parse(e) => write!(
+                f,
+                "cannot parse asset at line {}: {}",
+                e.line_number, e.message
+            ),
+            AssetError::EmptyMesh(path) => write!(f, "asset {} contains no triangles", path),
+            AssetError::InvalidBakedMesh(path) => {
+                write!(f, "{} is not a valid baked mesh", path)
+            }
+            AssetError::InvalidDetail(path) => {
+                write!(f, "{} does not end in a number of cells after the #", path)
+            }
+            AssetError::InvalidImage(message) => write!(f, "invalid image: {}", message),
+            AssetError::TessBuild(e) => write!(f, "cannot build tesselation: {:?}", e),
+            AssetError::TessMap(e) => write!(f, "cannot map tesselation: {}", e),
+            AssetError::Texture(e) => write!(f, "cannot build texture: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AssetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AssetError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for AssetError {
+    fn from(e: io::Error) -> Self {
+        AssetError::Io(e)
+    }
+}
+
+impl From<ParseError> for AssetError {
+    fn from(e: ParseError) -> Self {
+        AssetError::Parse(e)
+    }
+}
+
+impl From<TessError> for AssetError {
+    fn from(e: TessError) -> Self {
+        AssetError::TessBuild(e)
+    }
+}
+
+impl From<TessMapError> for AssetError {
+    fn from(e: TessMapError) -> Self {
+        AssetError::TessMap(e)
+    }
+}
+
+impl From<TextureError> for AssetError {
+    fn from(e: TextureError) -> Self {
+        AssetError::Texture(e)
+    }
+}
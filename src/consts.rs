@@ -1,19 +1,22 @@
 use crate::data::AABB;
 use doem_math::Vector3;
 
+pub const MODELS_DIR: &str = "models";
+
 // BULLET
 lazy_static! {
     pub static ref BULLET_COLLIDER_HALF_SIZE: Vector3 = Vector3::from([[1.0], [1.0], [1.0]]);
-    pub static ref BULLET_SCALE: Vector3 = Vector3::from([[2.0], [2.0], [2.0]]);
 }
-pub const BULLET_OBJ_PATH: &str = "models/bullet.obj";
 
 // Starship
-lazy_static! {
-    pub static ref STARSHIP_BULLET_VELOCITY: Vector3 = Vector3::from([[5.0], [0.0], [0.0]]);
-}
-pub const STARSHIP_BULLET_DAMAGE: f32 = 20.0;
 pub const STARSHIP_OBJ_PATH: &str = "models/starship.obj";
+pub const PLAYER_HEALTH: f32 = 100.0;
+// Respawns before the game is over
+pub const PLAYER_LIVES: u32 = 3;
+
+// Weapons, by their name in the weapon definition file
+pub const PLAYER_WEAPON: &str = "cannon";
+pub const ENEMY_WEAPON: &str = "blaster";
 
 // DONUT
 pub const DONUT_OBJ_PATH: &str = "models/donut.obj";
@@ -28,6 +31,9 @@ lazy_static! {
     pub static ref NONDESCRIPTCIRCLE_MAX_SCALE: Vector3 = Vector3::from([[10.0], [10.0], [10.0]]);
 }
 pub const NONDESCRIPTCIRCLE_OBJ_PATH: &str = "models/nondescript_circle.obj";
+// Simplified to this many cells along its longest side from this distance on
+pub const NONDESCRIPTCIRCLE_LODS: [(u32, f32); 2] = [(24, 900.0), (8, 1800.0)];
+pub const LOD_HYSTERESIS: f32 = 50.0;
 
 // reference plane
 pub const REFERENCEPLANE_OBJ_PATH: &str = "models/reference_plane.obj";
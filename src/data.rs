@@ -1,7 +1,81 @@
-use doem_math::Vector3;
+use doem_math::{Matrix4, Vector3};
 
 #[derive(Clone)]
 pub struct AABB {
     pub middle_point: Vector3,
     pub half_size: Vector3,
 }
+
+impl AABB {
+    // The smallest axis aligned box which contains this box after the transformation
+    pub fn transformed(&self, transform: &Matrix4) -> AABB {
+        let middle_point = (transform * &self.middle_point.dimension_hop()).dimension_hop();
+        let mut half_size = Vector3::origin();
+        for i in 0..3 {
+            for j in 0..3 {
+                half_size[i][0] += transform[i][j].abs() * self.half_size[j][0];
+            }
+        }
+        AABB {
+            middle_point,
+            half_size,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use doem_math::PI;
+
+    fn assert_vector(actual: &Vector3, expected: [f32; 3]) {
+        for i in 0..3 {
+            assert!(
+                (actual[i][0] - expected[i]).abs() < 0.0001,
+                "expected {:?}, got {}",
+                expected,
+                actual
+            );
+        }
+    }
+
+    fn aabb() -> AABB {
+        AABB {
+            middle_point: Vector3::from([[1.0], [0.0], [0.0]]),
+            half_size: Vector3::from([[1.0], [2.0], [3.0]]),
+        }
+    }
+
+    #[test]
+    fn identity_keeps_the_box() {
+        let transformed = aabb().transformed(&Matrix4::identity());
+        assert_vector(&transformed.middle_point, [1.0, 0.0, 0.0]);
+        assert_vector(&transformed.half_size, [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn translation_and_scaling() {
+        let translation = Matrix4::get_translation(&Vector3::from([[10.0], [0.0], [-5.0]]));
+        let scaling = Matrix4::get_scaling(&Vector3::from([[2.0], [2.0], [2.0]]));
+        let transformed = aabb().transformed(&(&translation * &scaling));
+        assert_vector(&transformed.middle_point, [12.0, 0.0, -5.0]);
+        assert_vector(&transformed.half_size, [2.0, 4.0, 6.0]);
+    }
+
+    #[test]
+    fn rotation_swaps_the_axes() {
+        let transformed = aabb().transformed(&Matrix4::get_rotation_y(PI / 2.0));
+        assert_vector(&transformed.middle_point, [0.0, 0.0, -1.0]);
+        assert_vector(&transformed.half_size, [3.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn rotated_boxes_grow_to_contain_their_corners() {
+        let transformed = aabb().transformed(&Matrix4::get_rotation_z(PI / 4.0));
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        assert_vector(
+            &transformed.half_size,
+            [half * 1.0 + half * 2.0, half * 1.0 + half * 2.0, 3.0],
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use specs::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Steering {
+    // Straight at the target
+    Seek,
+    // Straight away from the target
+    Flee,
+    // At where the target will be when the pilot gets there
+    Pursue,
+    // Like seek, but slowing down to stop at the target
+    Arrive,
+    // Circling the target at the given distance
+    Orbit { radius: f32 },
+}
+
+impl Steering {
+    // The steerings which need no parameters
+    pub fn parse(name: &str) -> Option<Steering> {
+        match name {
+            "seek" => Some(Steering::Seek),
+            "flee" => Some(Steering::Flee),
+            "pursue" => Some(Steering::Pursue),
+            "arrive" => Some(Steering::Arrive),
+            _ => None,
+        }
+    }
+}
+
+// Flies the entity like a player would, by turning it and changing its forward velocity.
+// The thrust of its Thruster is the most the speed can change in a tick.
+pub struct AiPilot {
+    pub steering: Steering,
+    // The entity with the Camera when none is given
+    pub target: Option<Entity>,
+    pub max_speed: f32,
+    // Radians per tick
+    pub turn_rate: f32,
+    // Where arrive starts to slow down
+    pub slowing_radius: f32,
+}
+
+impl AiPilot {
+    pub fn new(steering: Steering) -> Self {
+        AiPilot {
+            steering,
+            target: None,
+            max_speed: 6.0,
+            turn_rate: 0.03,
+            slowing_radius: 300.0,
+        }
+    }
+}
+
+impl Component for AiPilot {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,32 @@
+use crate::ecs::components::damage::DamageType;
+use specs::prelude::*;
+
+// The part of every damage type which is ignored, from 0 to 1
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Resistances {
+    pub kinetic: f32,
+    pub energy: f32,
+    pub explosive: f32,
+}
+
+impl Resistances {
+    pub fn get(&self, damage_type: DamageType) -> f32 {
+        match damage_type {
+            DamageType::Kinetic => self.kinetic,
+            DamageType::Energy => self.energy,
+            DamageType::Explosive => self.explosive,
+        }
+    }
+}
+
+pub struct Armor {
+    pub resistances: Resistances,
+    // Taken off every hit which gets through the shield
+    pub reduction: f32,
+    // Ticks the entity can not be damaged after it got hit, 0 for none
+    pub invulnerability: f32,
+}
+
+impl Component for Armor {
+    type Storage = VecStorage<Self>;
+}
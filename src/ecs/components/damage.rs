@@ -1,7 +1,26 @@
 use specs::prelude::*;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DamageType {
+    Kinetic,
+    Energy,
+    Explosive,
+}
+
+impl DamageType {
+    pub fn parse(name: &str) -> Option<DamageType> {
+        match name {
+            "kinetic" => Some(DamageType::Kinetic),
+            "energy" => Some(DamageType::Energy),
+            "explosive" => Some(DamageType::Explosive),
+            _ => None,
+        }
+    }
+}
+
 pub struct Damage {
     pub damage: f32,
+    pub damage_type: DamageType,
     pub despawn_entity_on_impact: bool,
     pub damage_dealer: Entity,
 }
@@ -0,0 +1,12 @@
+use specs::prelude::*;
+
+// Whether the Gun of the entity should fire this tick. The keyboard or an AiPilot sets it,
+// the ShootSystem fires and resets it.
+#[derive(Default)]
+pub struct FireIntent {
+    pub firing: bool,
+}
+
+impl Component for FireIntent {
+    type Storage = VecStorage<Self>;
+}
@@ -1,11 +1,38 @@
-use doem_math::Vector3;
+use crate::weapons::Weapon;
 use specs::prelude::*;
 
 pub struct Gun {
-    pub damage: f32,
-    pub velocity: Vector3,
-    pub despawn_bullet_on_impact: bool,
+    pub weapon: Weapon,
+    // Ticks until it can fire again
+    pub cooldown: f32,
+    // Projectiles left in the magazine
+    pub ammo: u32,
+    // Ticks until the magazine is full again once it is empty
+    pub reloading: f32,
 }
+
+impl Gun {
+    pub fn new(weapon: Weapon) -> Self {
+        Gun {
+            ammo: weapon.magazine,
+            weapon,
+            cooldown: 0.0,
+            reloading: 0.0,
+        }
+    }
+    pub fn ready(&self) -> bool {
+        self.cooldown <= 0.0 && (self.weapon.magazine == 0 || self.ammo > 0)
+    }
+    // How many barrels the next shot fires, the last rounds of a magazine do not fill them all
+    pub fn barrels(&self) -> u32 {
+        if self.weapon.magazine == 0 {
+            self.weapon.barrels
+        } else {
+            self.weapon.barrels.min(self.ammo)
+        }
+    }
+}
+
 impl Component for Gun {
     type Storage = VecStorage<Self>;
 }
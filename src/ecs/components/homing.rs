@@ -0,0 +1,12 @@
+use specs::prelude::*;
+
+// Turns the projectile towards the target while it flies
+pub struct Homing {
+    pub target: Entity,
+    // Radians per tick
+    pub turn_rate: f32,
+}
+
+impl Component for Homing {
+    type Storage = VecStorage<Self>;
+}
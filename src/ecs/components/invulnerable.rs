@@ -0,0 +1,10 @@
+use specs::prelude::*;
+
+// Nothing damages the entity until the ticks run out, then the component is removed
+pub struct Invulnerable {
+    pub ticks: f32,
+}
+
+impl Component for Invulnerable {
+    type Storage = VecStorage<Self>;
+}
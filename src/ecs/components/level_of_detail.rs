@@ -0,0 +1,157 @@
+use crate::ecs::components::shape::Shape;
+use specs::prelude::*;
+
+pub struct LodLevel {
+    // Starts as a Shape::Unit and is initialized by the GLSystem like any other Shape
+    pub shape: Shape,
+    // The level is used when the camera is at least this far away
+    pub distance: f32,
+}
+
+impl LodLevel {
+    pub fn new(obj_path: &str, distance: f32) -> Self {
+        Self {
+            shape: Shape::Unit {
+                obj_path: obj_path.to_owned(),
+            },
+            distance,
+        }
+    }
+}
+
+// Coarser versions of an entity's Shape, the Shape itself is drawn up close
+pub struct LevelOfDetail {
+    // Sorted by distance, from near to far
+    pub levels: Vec<LodLevel>,
+    // How far past a threshold the camera has to move before the level changes
+    pub hysteresis: f32,
+    // 0 is the Shape itself, n is levels[n - 1]
+    pub current_level: usize,
+}
+
+impl LevelOfDetail {
+    pub fn new(levels: Vec<LodLevel>, hysteresis: f32) -> Self {
+        Self {
+            levels,
+            hysteresis,
+            current_level: 0,
+        }
+    }
+    pub fn select(&mut self, distance: f32) -> usize {
+        while self.current_level < self.levels.len()
+            && distance > self.levels[self.current_level].distance + self.hysteresis
+        {
+            self.current_level += 1;
+        }
+        while self.current_level > 0
+            && distance < self.levels[self.current_level - 1].distance - self.hysteresis
+        {
+            self.current_level -= 1;
+        }
+        self.current_level
+    }
+    // The mesh of the level, or of the next more detailed level when it is still loading or
+    // could not be loaded. None means the Shape itself is drawn.
+    pub fn loaded_mesh<F>(&self, level: usize, failed: F) -> Option<usize>
+    where
+        F: Fn(usize) -> bool,
+    {
+        self.levels[..level]
+            .iter()
+            .rev()
+            .filter_map(|level| match &level.shape {
+                Shape::Init { tess, .. } if !failed(tess.id()) => Some(tess.id()),
+                _ => None,
+            })
+            .next()
+    }
+}
+
+impl Component for LevelOfDetail {
+    type Storage = VecStorage<Self>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::AABB;
+    use crate::tess_manager::TessHandle;
+    use doem_math::Vector3;
+
+    const PLACEHOLDER: usize = 99;
+
+    fn lod() -> LevelOfDetail {
+        LevelOfDetail::new(
+            vec![
+                LodLevel::new("models/donut.obj#16", 100.0),
+                LodLevel::new("models/donut.obj#4", 200.0),
+            ],
+            10.0,
+        )
+    }
+
+    #[test]
+    fn starts_at_full_detail() {
+        assert_eq!(lod().select(0.0), 0);
+    }
+
+    #[test]
+    fn switches_only_past_the_hysteresis() {
+        let mut lod = lod();
+        assert_eq!(lod.select(105.0), 0);
+        assert_eq!(lod.select(111.0), 1);
+        // Coming back, the level holds until the camera is the hysteresis closer
+        assert_eq!(lod.select(95.0), 1);
+        assert_eq!(lod.select(89.0), 0);
+    }
+
+    #[test]
+    fn skips_levels_when_moving_far() {
+        let mut lod = lod();
+        assert_eq!(lod.select(500.0), 2);
+        assert_eq!(lod.select(195.0), 2);
+        assert_eq!(lod.select(150.0), 1);
+        assert_eq!(lod.select(0.0), 0);
+    }
+
+    fn loaded(level: &mut LodLevel, id: usize) {
+        level.shape = Shape::Init {
+            obj_path: String::new(),
+            tess: TessHandle::new(id),
+            bounding_box: AABB {
+                middle_point: Vector3::from([[0.0], [0.0], [0.0]]),
+                half_size: Vector3::from([[1.0], [1.0], [1.0]]),
+            },
+            bounding_box_tess: None,
+        };
+    }
+
+    #[test]
+    fn draws_the_selected_level() {
+        let mut lod = lod();
+        loaded(&mut lod.levels[0], 1);
+        loaded(&mut lod.levels[1], 2);
+        let failed = |id| id == PLACEHOLDER;
+        assert_eq!(lod.loaded_mesh(0, failed), None);
+        assert_eq!(lod.loaded_mesh(1, failed), Some(1));
+        assert_eq!(lod.loaded_mesh(2, failed), Some(2));
+    }
+
+    #[test]
+    fn failed_levels_fall_back_to_more_detail() {
+        let mut lod = lod();
+        loaded(&mut lod.levels[0], 1);
+        loaded(&mut lod.levels[1], PLACEHOLDER);
+        let failed = |id| id == PLACEHOLDER;
+        assert_eq!(lod.loaded_mesh(2, failed), Some(1));
+        loaded(&mut lod.levels[0], PLACEHOLDER);
+        assert_eq!(lod.loaded_mesh(2, failed), None);
+    }
+
+    #[test]
+    fn loading_levels_fall_back_to_more_detail() {
+        let mut lod = lod();
+        loaded(&mut lod.levels[0], 1);
+        assert_eq!(lod.loaded_mesh(2, |id| id == PLACEHOLDER), Some(1));
+    }
+}
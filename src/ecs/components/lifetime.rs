@@ -0,0 +1,33 @@
+use doem_math::Vector3;
+use specs::prelude::*;
+
+// Despawns the entity once it is older or has travelled further than its limits
+pub struct Lifetime {
+    // In ticks
+    pub max_age: Option<f32>,
+    pub max_distance: Option<f32>,
+    pub age: f32,
+    pub travelled: f32,
+    // Where it was last tick, None before its first tick
+    pub last_position: Option<Vector3>,
+}
+
+impl Lifetime {
+    pub fn new(max_age: Option<f32>, max_distance: Option<f32>) -> Self {
+        Lifetime {
+            max_age,
+            max_distance,
+            age: 0.0,
+            travelled: 0.0,
+            last_position: None,
+        }
+    }
+    pub fn expired(&self) -> bool {
+        self.max_age.map_or(false, |max| self.age >= max)
+            || self.max_distance.map_or(false, |max| self.travelled >= max)
+    }
+}
+
+impl Component for Lifetime {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,12 @@
+use specs::prelude::*;
+
+// Respawns the entity at the RespawnPoint instead of destroying it, as long as lives are left
+pub struct Lives {
+    pub remaining: u32,
+    // The Health it respawns with
+    pub health: f32,
+}
+
+impl Component for Lives {
+    type Storage = VecStorage<Self>;
+}
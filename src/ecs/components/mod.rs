@@ -1,12 +1,22 @@
+pub mod ai_pilot;
+pub mod armor;
 pub mod camera;
 pub mod collider;
 pub mod damage;
 pub mod despawn;
+pub mod fire_intent;
 pub mod gun;
 pub mod health;
+pub mod homing;
+pub mod invulnerable;
+pub mod level_of_detail;
+pub mod lifetime;
+pub mod lives;
+pub mod particle_emitter;
 pub mod physics;
 pub mod pulsate;
 pub mod shape;
+pub mod shield;
 pub mod thruster;
 pub mod transform;
 pub mod transformable;
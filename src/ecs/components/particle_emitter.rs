@@ -0,0 +1,97 @@
+use doem_math::Vector3;
+use specs::prelude::*;
+
+// Spawns particles at its entity, the offset and velocity are in the entity's local space.
+// The ParticleSystem emits rate particles every tick, effects like explosions emit a burst
+// of them at once through the Particles resource.
+#[derive(Clone)]
+pub struct ParticleEmitter {
+    pub active: bool,
+    // Particles per tick, the fractions carry over to the next tick
+    pub rate: f32,
+    pub burst: usize,
+    // In ticks
+    pub lifetime: u32,
+    pub offset: Vector3,
+    pub velocity: Vector3,
+    // Every axis of the velocity gets a random extra between -spread and spread
+    pub velocity_spread: f32,
+    // RGBA, the color and size blend from start to end over the lifetime of a particle
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub start_size: f32,
+    pub end_size: f32,
+    pub carry: f32,
+}
+
+impl ParticleEmitter {
+    // Exhaust behind a ship, the ship flies along its local -X axis
+    pub fn thrust() -> Self {
+        Self {
+            active: false,
+            rate: 2.0,
+            burst: 0,
+            lifetime: 30,
+            offset: Vector3::from([[6.0], [0.0], [0.0]]),
+            velocity: Vector3::from([[1.5], [0.0], [0.0]]),
+            velocity_spread: 0.2,
+            start_color: [0.4, 0.7, 1.0, 1.0],
+            end_color: [0.1, 0.1, 0.6, 0.0],
+            start_size: 3.0,
+            end_size: 8.0,
+            carry: 0.0,
+        }
+    }
+    pub fn bullet_trail() -> Self {
+        Self {
+            active: true,
+            rate: 0.5,
+            burst: 0,
+            lifetime: 20,
+            offset: Vector3::from([[0.0], [0.0], [0.0]]),
+            velocity: Vector3::from([[0.0], [0.0], [0.0]]),
+            velocity_spread: 0.05,
+            start_color: [1.0, 0.9, 0.5, 0.8],
+            end_color: [1.0, 0.3, 0.0, 0.0],
+            start_size: 1.5,
+            end_size: 0.5,
+            carry: 0.0,
+        }
+    }
+    pub fn muzzle_flash() -> Self {
+        Self {
+            active: false,
+            rate: 0.0,
+            burst: 12,
+            lifetime: 6,
+            offset: Vector3::from([[-6.0], [0.0], [0.0]]),
+            velocity: Vector3::from([[-1.0], [0.0], [0.0]]),
+            velocity_spread: 0.4,
+            start_color: [1.0, 1.0, 0.7, 1.0],
+            end_color: [1.0, 0.5, 0.1, 0.0],
+            start_size: 4.0,
+            end_size: 1.0,
+            carry: 0.0,
+        }
+    }
+    pub fn explosion() -> Self {
+        Self {
+            active: false,
+            rate: 0.0,
+            burst: 200,
+            lifetime: 60,
+            offset: Vector3::from([[0.0], [0.0], [0.0]]),
+            velocity: Vector3::from([[0.0], [0.0], [0.0]]),
+            velocity_spread: 2.0,
+            start_color: [1.0, 0.9, 0.4, 1.0],
+            end_color: [0.6, 0.1, 0.0, 0.0],
+            start_size: 6.0,
+            end_size: 20.0,
+            carry: 0.0,
+        }
+    }
+}
+
+impl Component for ParticleEmitter {
+    type Storage = VecStorage<Self>;
+}
@@ -1,16 +1,22 @@
 use crate::data::AABB;
+use crate::tess_manager::TessHandle;
 use specs::prelude::*;
 
 #[derive(Clone)]
 pub enum Shape {
     Init {
-        tess_id: usize,
+        obj_path: String,
+        tess: TessHandle,
         bounding_box: AABB,
-        bounding_box_tess_id: Option<usize>,
+        bounding_box_tess: Option<TessHandle>,
     },
     Unit {
         obj_path: String,
     },
+    // The obj file is being parsed in the background by the TessManager
+    Loading {
+        obj_path: String,
+    },
 }
 
 impl Component for Shape {
@@ -0,0 +1,28 @@
+use specs::prelude::*;
+
+// Takes the damage before the Health does and recharges when the entity is left alone
+pub struct Shield {
+    pub max: f32,
+    pub current: f32,
+    // Per tick
+    pub regeneration: f32,
+    // Ticks after a hit before it starts recharging
+    pub delay: f32,
+    pub since_hit: f32,
+}
+
+impl Shield {
+    pub fn new(max: f32, regeneration: f32, delay: f32) -> Self {
+        Shield {
+            max,
+            current: max,
+            regeneration,
+            delay,
+            since_hit: delay,
+        }
+    }
+}
+
+impl Component for Shield {
+    type Storage = VecStorage<Self>;
+}
@@ -1,33 +1,186 @@
+use crate::ecs::resources::profiler::Recorder;
+use crate::ecs::systems::ai_system::AiSystem;
+use crate::ecs::systems::ai_targeting_system::AiTargetingSystem;
 use crate::ecs::systems::camera_system::CameraSystem;
 use crate::ecs::systems::collision_detector_3::CollisionDetector3;
+use crate::ecs::systems::console_system::ConsoleSystem;
 use crate::ecs::systems::damage_system::DamageSystem;
 use crate::ecs::systems::despawn_system::DespawnSystem;
+use crate::ecs::systems::destruction_system::DestructionSystem;
 use crate::ecs::systems::gl_system::GLSystem;
+use crate::ecs::systems::homing_system::HomingSystem;
+use crate::ecs::systems::hud_system::HudSystem;
+use crate::ecs::systems::particle_system::ParticleSystem;
 use crate::ecs::systems::physics_system::PhysicsSystem;
+use crate::ecs::systems::player_input_system::PlayerInputSystem;
+use crate::ecs::systems::profiler_system::ProfilerSystem;
 use crate::ecs::systems::pulsate_system::PulsateSystem;
+use crate::ecs::systems::respawn_system::RespawnSystem;
+use crate::ecs::systems::score_system::ScoreSystem;
 use crate::ecs::systems::shoot_system::ShootSystem;
 use crate::ecs::systems::thruster_system::ThrusterSystem;
 use crate::ecs::systems::transformations_system::TransformationsSystem;
+use crate::logger::Span;
+use crate::screenshot::Frames;
+use crate::settings::Settings;
 use luminance_glfw::GlfwSurface;
 use specs::prelude::*;
 use std::sync::Arc;
 use std::sync::Mutex;
 
+// Times every run of the system it wraps, the timings are logged as spans and recorded for
+// the Profiler
+pub struct Timed<S> {
+    system: S,
+    name: &'static str,
+    recorder: Recorder,
+}
+
+impl<'a, S> System<'a> for Timed<S>
+where
+    S: System<'a>,
+{
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let _span = Span::enter(self.name);
+        let _scope = self.recorder.scope(self.name);
+        self.system.run(data);
+    }
+    fn setup(&mut self, world: &mut World) {
+        self.system.setup(world);
+    }
+    fn dispose(self, world: &mut World) {
+        self.system.dispose(world);
+    }
+}
+
+// A DispatcherBuilder which wraps every system it is given in Timed
+struct TimedBuilder<'a, 'b> {
+    builder: DispatcherBuilder<'a, 'b>,
+    recorder: Recorder,
+}
+
+impl<'a, 'b> TimedBuilder<'a, 'b> {
+    fn new(recorder: Recorder) -> Self {
+        TimedBuilder {
+            builder: DispatcherBuilder::new(),
+            recorder,
+        }
+    }
+    fn with<S>(self, system: S, name: &'static str, dep: &[&str]) -> Self
+    where
+        S: for<'c> System<'c> + Send + 'a,
+    {
+        let timed = Timed {
+            system,
+            name,
+            recorder: self.recorder.clone(),
+        };
+        TimedBuilder {
+            builder: self.builder.with(timed, name, dep),
+            recorder: self.recorder,
+        }
+    }
+    fn with_thread_local<S>(self, system: S, name: &'static str) -> Self
+    where
+        S: for<'c> System<'c> + 'b,
+    {
+        let timed = Timed {
+            system,
+            name,
+            recorder: self.recorder.clone(),
+        };
+        TimedBuilder {
+            builder: self.builder.with_thread_local(timed),
+            recorder: self.recorder,
+        }
+    }
+    fn build(self) -> Dispatcher<'a, 'b> {
+        self.builder.build()
+    }
+}
+
 pub struct DoemDispatcher;
 
 impl DoemDispatcher {
-    pub fn new<'a, 'b>(surface: GlfwSurface, should_quit: Arc<Mutex<bool>>) -> Dispatcher<'a, 'b> {
-        DispatcherBuilder::new()
+    pub fn new<'a, 'b>(
+        surface: GlfwSurface,
+        settings: &Settings,
+        should_quit: Arc<Mutex<bool>>,
+        hot_reload: bool,
+        wait_for_loads: bool,
+        frames: Option<Frames>,
+        recorder: Recorder,
+    ) -> Dispatcher<'a, 'b> {
+        TimedBuilder::new(recorder)
             .with(CollisionDetector3, "collision_detector_3", &[])
-            .with(PhysicsSystem, "physics_system", &[])
+            .with(AiSystem, "ai_system", &[])
+            .with(HomingSystem, "homing_system", &[])
+            .with(
+                PhysicsSystem,
+                "physics_system",
+                &["ai_system", "homing_system"],
+            )
             .with(TransformationsSystem, "transformations_system", &[])
             .with(PulsateSystem, "pulsate_system", &[])
-            .with(ShootSystem, "shoot_system", &[])
+            .with(PlayerInputSystem, "player_input_system", &[])
+            .with(AiTargetingSystem, "ai_targeting_system", &["ai_system"])
+            .with(
+                ShootSystem,
+                "shoot_system",
+                &["player_input_system", "ai_targeting_system"],
+            )
             .with(ThrusterSystem, "thruster_system", &[])
             .with(DamageSystem, "damage_system", &["collision_detector_3"])
             .with(DespawnSystem, "despawn_system", &[])
             .with(CameraSystem, "camera_system", &[])
-            .with_thread_local(GLSystem::new(surface, should_quit))
+            .with(
+                ParticleSystem,
+                "particle_system",
+                &[
+                    "physics_system",
+                    "thruster_system",
+                    "shoot_system",
+                    "damage_system",
+                ],
+            )
+            .with(
+                HudSystem,
+                "hud_system",
+                &["physics_system", "thruster_system", "damage_system"],
+            )
+            .with(ConsoleSystem, "console_system", &["hud_system"])
+            // Everything reacting to destructions runs between the damage and destruction systems
+            .with(
+                RespawnSystem,
+                "respawn_system",
+                &["damage_system", "console_system"],
+            )
+            .with(ScoreSystem, "score_system", &["respawn_system"])
+            .with(
+                DestructionSystem,
+                "destruction_system",
+                &[
+                    "damage_system",
+                    "despawn_system",
+                    "respawn_system",
+                    "score_system",
+                    "console_system",
+                ],
+            )
+            .with(ProfilerSystem, "profiler_system", &["console_system"])
+            .with_thread_local(
+                GLSystem::new(
+                    surface,
+                    settings,
+                    should_quit,
+                    hot_reload,
+                    wait_for_loads,
+                    frames,
+                ),
+                "gl_system",
+            )
             .build()
     }
 }
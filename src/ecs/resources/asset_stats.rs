@@ -0,0 +1,8 @@
+// Written by the GLSystem after every frame
+#[derive(Default)]
+pub struct AssetStats {
+    pub live_tesselations: usize,
+    pub cached_meshes: usize,
+    pub aabb_meshes: usize,
+    pub pending_loads: usize,
+}
@@ -0,0 +1,30 @@
+// What the GLSystem draws behind the scene, every scene can set its own
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Background {
+    pub skybox: Option<Skybox>,
+    pub starfield: Option<Starfield>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Skybox {
+    // A directory with the faces px.ppm, nx.ppm, py.ppm, ny.ppm, pz.ppm and nz.ppm
+    Faces {
+        dir: String,
+    },
+    // Blends from the nadir color below, through the horizon, to the zenith color above
+    Gradient {
+        zenith: [f32; 3],
+        horizon: [f32; 3],
+        nadir: [f32; 3],
+    },
+}
+
+// Points scattered in layers of boxes around the camera, the nearer layers move faster
+#[derive(Clone, Debug, PartialEq)]
+pub struct Starfield {
+    pub layers: u32,
+    pub stars_per_layer: u32,
+    // The size of the box of the nearest layer, every next layer is one size bigger
+    pub layer_size: f32,
+    pub brightness: f32,
+}
@@ -1,6 +1,7 @@
 use specs::prelude::*;
 use std::collections::BTreeSet;
 
+// The entities touching each other this tick, every pair once with the lower id first
 pub struct Collisions(pub BTreeSet<(Entity, Entity)>);
 
 impl Default for Collisions {
@@ -0,0 +1,235 @@
+use crate::ecs::components::ai_pilot::Steering;
+use luminance_glfw::{Action, Key, WindowEvent};
+use specs::prelude::*;
+
+// The console only keeps this many lines of output around
+const MAX_LINES: usize = 200;
+
+pub const HELP: [&str; 10] = [
+    "help                      shows this list",
+    "list                      lists every entity and its components",
+    "inspect [id]              shows an entity in the inspector, without an id it closes",
+    "set <id> <field> <values> sets position, scale, velocity, health, zoom or offset",
+    "spawn <obj> at <x> <y> <z> spawns a model from the models directory",
+    "kill <id>                 destroys an entity",
+    "steer <id> <steering>     seek, flee, pursue, arrive or orbit <radius> around the target",
+    "timescale <scale>         speeds the world up or slows it down, 0 pauses it",
+    "clear                     clears the console",
+    "the key below escape toggles the console, up brings back the previous command",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Field {
+    Position,
+    Scale,
+    Velocity,
+    Health,
+    Zoom,
+    Offset,
+}
+
+impl Field {
+    fn parse(name: &str) -> Result<Field, String> {
+        match name {
+            "position" => Ok(Field::Position),
+            "scale" => Ok(Field::Scale),
+            "velocity" => Ok(Field::Velocity),
+            "health" => Ok(Field::Health),
+            "zoom" => Ok(Field::Zoom),
+            "offset" => Ok(Field::Offset),
+            _ => Err(format!("unknown field `{}`", name)),
+        }
+    }
+    // How many numbers a value of the field takes
+    fn arity(self) -> usize {
+        match self {
+            Field::Health | Field::Zoom => 1,
+            _ => 3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Help,
+    List,
+    Inspect(Option<u32>),
+    Set {
+        id: u32,
+        field: Field,
+        values: Vec<f32>,
+    },
+    Spawn {
+        obj: String,
+        position: [f32; 3],
+    },
+    Kill(u32),
+    Steer {
+        id: u32,
+        steering: Steering,
+    },
+    TimeScale(f32),
+    Clear,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.len() >= 3 && words[0] == "set" {
+            let field = Field::parse(words[2])?;
+            let values = &words[3..];
+            if values.len() != field.arity() {
+                return Err(format!("{:?} takes {} numbers", field, field.arity()));
+            }
+            return Ok(Command::Set {
+                id: Self::id(words[1])?,
+                field,
+                values: Self::numbers(values)?,
+            });
+        }
+        match words.as_slice() {
+            ["help"] => Ok(Command::Help),
+            ["list"] => Ok(Command::List),
+            ["inspect"] => Ok(Command::Inspect(None)),
+            ["inspect", id] => Ok(Command::Inspect(Some(Self::id(id)?))),
+            ["spawn", obj, "at", x, y, z] => {
+                let position = Self::numbers(&[*x, *y, *z])?;
+                Ok(Command::Spawn {
+                    obj: (*obj).to_owned(),
+                    position: [position[0], position[1], position[2]],
+                })
+            }
+            ["kill", id] => Ok(Command::Kill(Self::id(id)?)),
+            ["steer", id, "orbit", radius] => match radius.parse::<f32>() {
+                Ok(radius) if radius > 0.0 => Ok(Command::Steer {
+                    id: Self::id(id)?,
+                    steering: Steering::Orbit { radius },
+                }),
+                _ => Err(format!("`{}` is not a valid radius", radius)),
+            },
+            ["steer", id, name] => match Steering::parse(name) {
+                Some(steering) => Ok(Command::Steer {
+                    id: Self::id(id)?,
+                    steering,
+                }),
+                None => Err(format!("unknown steering `{}`", name)),
+            },
+            ["timescale", scale] => match scale.parse::<f32>() {
+                Ok(scale) if scale >= 0.0 => Ok(Command::TimeScale(scale)),
+                _ => Err(format!("`{}` is not a valid time scale", scale)),
+            },
+            ["clear"] => Ok(Command::Clear),
+            _ => Err(format!("unknown command `{}`, try help", line.trim())),
+        }
+    }
+    fn id(word: &str) -> Result<u32, String> {
+        word.parse::<u32>()
+            .map_err(|_| format!("`{}` is not an entity id", word))
+    }
+    fn numbers(words: &[&str]) -> Result<Vec<f32>, String> {
+        words
+            .iter()
+            .map(|w| {
+                w.parse::<f32>()
+                    .map_err(|_| format!("`{}` is not a number", w))
+            })
+            .collect()
+    }
+}
+
+// Typed into by the GLSystem while it is open, the ConsoleSystem runs the commands and draws it
+#[derive(Default)]
+pub struct Console {
+    pub open: bool,
+    pub input: String,
+    pub lines: Vec<String>,
+    // Entered but not run yet
+    pub commands: Vec<String>,
+    // Shown in the inspector, also while the console is closed
+    pub inspected: Option<Entity>,
+    history: Vec<String>,
+    history_index: usize,
+}
+
+impl Console {
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+    pub fn print<S>(&mut self, line: S)
+    where
+        S: Into<String>,
+    {
+        self.lines.push(line.into());
+        if self.lines.len() > MAX_LINES {
+            let overflow = self.lines.len() - MAX_LINES;
+            self.lines.drain(..overflow);
+        }
+    }
+    // Takes the keys and characters away from the game while the console is open
+    pub fn handle(&mut self, event: WindowEvent) {
+        match event {
+            // The toggle key is typed as well
+            WindowEvent::Char('`') => (),
+            WindowEvent::Char(c) => self.input.push(c),
+            WindowEvent::Key(Key::Backspace, _, action, _)
+                if action == Action::Press || action == Action::Repeat =>
+            {
+                self.input.pop();
+            }
+            WindowEvent::Key(Key::Enter, _, Action::Press, _) => self.submit(),
+            WindowEvent::Key(Key::Up, _, Action::Press, _) if self.history_index > 0 => {
+                self.history_index -= 1;
+                self.input = self.history[self.history_index].clone();
+            }
+            WindowEvent::Key(Key::Down, _, Action::Press, _)
+                if self.history_index < self.history.len() =>
+            {
+                self.history_index += 1;
+                self.input = self
+                    .history
+                    .get(self.history_index)
+                    .cloned()
+                    .unwrap_or_default();
+            }
+            WindowEvent::Key(Key::Escape, _, Action::Release, _) => self.open = false,
+            _ => (),
+        }
+    }
+    fn submit(&mut self) {
+        let command = self.input.trim().to_owned();
+        self.input.clear();
+        if command.is_empty() {
+            return;
+        }
+        self.print(format!("> {}", command));
+        self.history.push(command.clone());
+        self.history_index = self.history.len();
+        self.commands.push(command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steer() {
+        assert_eq!(
+            Command::parse("steer 4 arrive"),
+            Ok(Command::Steer {
+                id: 4,
+                steering: Steering::Arrive
+            })
+        );
+        assert_eq!(
+            Command::parse("steer 4 orbit 250"),
+            Ok(Command::Steer {
+                id: 4,
+                steering: Steering::Orbit { radius: 250.0 }
+            })
+        );
+        assert!(Command::parse("steer 4 orbit -1").is_err());
+        assert!(Command::parse("steer 4 wander").is_err());
+        assert!(Command::parse("steer me seek").is_err());
+    }
+}
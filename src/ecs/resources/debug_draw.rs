@@ -0,0 +1,121 @@
+use crate::data::AABB;
+use doem_math::{Matrix4, Vector3, PI};
+
+const SPHERE_SEGMENTS: usize = 24;
+const CONTACT_SIZE: f32 = 5.0;
+
+pub const RED: [f32; 3] = [1.0, 0.0, 0.0];
+pub const GREEN: [f32; 3] = [0.0, 1.0, 0.0];
+pub const BLUE: [f32; 3] = [0.0, 0.0, 1.0];
+pub const YELLOW: [f32; 3] = [1.0, 1.0, 0.0];
+
+pub struct DebugLine {
+    pub from: [f32; 3],
+    pub to: [f32; 3],
+    pub color: [f32; 3],
+}
+
+// Any system can push shapes into this during a tick, the GLSystem draws them as lines and
+// clears them afterwards. Nothing is recorded while debug drawing is turned off.
+#[derive(Default)]
+pub struct DebugDraw {
+    pub enabled: bool,
+    pub lines: Vec<DebugLine>,
+}
+
+impl DebugDraw {
+    pub fn line(&mut self, from: &Vector3, to: &Vector3, color: [f32; 3]) {
+        self.push(Self::to_array(from), Self::to_array(to), color);
+    }
+    fn push(&mut self, from: [f32; 3], to: [f32; 3], color: [f32; 3]) {
+        if self.enabled {
+            self.lines.push(DebugLine { from, to, color });
+        }
+    }
+    pub fn aabb(&mut self, aabb: &AABB, color: [f32; 3]) {
+        self.obb(aabb, &Matrix4::identity(), color);
+    }
+    // A box which is transformed along with its entity
+    pub fn obb(&mut self, aabb: &AABB, transform: &Matrix4, color: [f32; 3]) {
+        if !self.enabled {
+            return;
+        }
+        let mut corners: Vec<[f32; 3]> = Vec::with_capacity(8);
+        for i in 0..8 {
+            let mut corner = aabb.middle_point.clone();
+            for axis in 0..3 {
+                let sign = if i & (1 << axis) == 0 { -1.0 } else { 1.0 };
+                corner[axis][0] += sign * aabb.half_size[axis][0];
+            }
+            let corner = (transform * &corner.dimension_hop()).dimension_hop();
+            corners.push(Self::to_array(&corner));
+        }
+        // Corners which differ in exactly one axis share an edge
+        for i in 0..8 {
+            for axis in 0..3 {
+                let j = i | (1 << axis);
+                if j != i {
+                    self.push(corners[i], corners[j], color);
+                }
+            }
+        }
+    }
+    // Three circles around the axes
+    pub fn sphere(&mut self, center: &Vector3, radius: f32, color: [f32; 3]) {
+        if !self.enabled {
+            return;
+        }
+        let center = Self::to_array(center);
+        let point = |axis: usize, angle: f32| {
+            let mut p = center;
+            p[(axis + 1) % 3] += radius * angle.cos();
+            p[(axis + 2) % 3] += radius * angle.sin();
+            p
+        };
+        for axis in 0..3 {
+            for i in 0..SPHERE_SEGMENTS {
+                let from = 2.0 * PI * i as f32 / SPHERE_SEGMENTS as f32;
+                let to = 2.0 * PI * (i + 1) as f32 / SPHERE_SEGMENTS as f32;
+                self.push(point(axis, from), point(axis, to), color);
+            }
+        }
+    }
+    // A line from the start along the vector, with a small head at its end
+    pub fn arrow(&mut self, start: &Vector3, vector: &Vector3, color: [f32; 3]) {
+        let length = vector.length();
+        if !self.enabled || length == 0.0 {
+            return;
+        }
+        let end = start + vector;
+        self.line(start, &end, color);
+
+        let direction = vector.normalize();
+        let up = if direction[1][0].abs() < 0.99 {
+            Vector3::from([[0.0], [1.0], [0.0]])
+        } else {
+            Vector3::from([[1.0], [0.0], [0.0]])
+        };
+        let side = direction.cross_product(&up).normalize();
+        let back = &end - &(&direction * (length * 0.2));
+        let wing = &side * (length * 0.1);
+        self.line(&end, &(&back + &wing), color);
+        self.line(&end, &(&back - &wing), color);
+    }
+    // A small cross where two entities touch
+    pub fn contact(&mut self, point: &Vector3, color: [f32; 3]) {
+        let point = Self::to_array(point);
+        for axis in 0..3 {
+            let mut from = point;
+            let mut to = point;
+            from[axis] -= CONTACT_SIZE;
+            to[axis] += CONTACT_SIZE;
+            self.push(from, to, color);
+        }
+    }
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+    fn to_array(v: &Vector3) -> [f32; 3] {
+        [v[0][0], v[1][0], v[2][0]]
+    }
+}
@@ -0,0 +1,34 @@
+use specs::prelude::*;
+
+// An entity ran out of Health, the killer is whoever fired the last hit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Destroyed {
+    pub entity: Entity,
+    pub killer: Option<Entity>,
+}
+
+// Everything which goes away this tick. Systems queue entities here instead of deleting them,
+// the systems which react to it run in between and the DestructionSystem deletes them at the
+// end of the tick. Taking an entity out of the lists keeps it alive.
+#[derive(Default)]
+pub struct Destructions {
+    pub destroyed: Vec<Destroyed>,
+    // Removed without any effect, like bullets on impact
+    pub despawned: Vec<Entity>,
+}
+
+impl Destructions {
+    pub fn destroy(&mut self, entity: Entity, killer: Option<Entity>) {
+        if !self.destroyed.iter().any(|d| d.entity == entity) {
+            self.destroyed.push(Destroyed { entity, killer });
+        }
+    }
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.despawned.contains(&entity) {
+            self.despawned.push(entity);
+        }
+    }
+    pub fn is_destroyed(&self, entity: Entity) -> bool {
+        self.destroyed.iter().any(|d| d.entity == entity)
+    }
+}
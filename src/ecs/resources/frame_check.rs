@@ -0,0 +1,7 @@
+// How the rendered frames matched the golden frames, written by the GLSystem when comparing
+#[derive(Default)]
+pub struct FrameCheck {
+    pub compared: u64,
+    // The frames which differ or have no golden frame
+    pub differing: Vec<u64>,
+}
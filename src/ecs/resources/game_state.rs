@@ -0,0 +1,22 @@
+use doem_math::{Matrix4, Vector3};
+
+// Where the player comes back after losing a life, set by every scene
+pub struct RespawnPoint {
+    pub position: Vector3,
+    pub orientation: Matrix4,
+}
+
+impl Default for RespawnPoint {
+    fn default() -> RespawnPoint {
+        RespawnPoint {
+            position: Vector3::origin(),
+            orientation: Matrix4::identity(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct GameState {
+    // The player was destroyed without lives left
+    pub game_over: bool,
+}
@@ -0,0 +1,247 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+// Where a HUD element goes, the offset points from the anchor towards the middle of the screen
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HudPosition {
+    pub anchor: Anchor,
+    pub offset: [f32; 2],
+}
+
+impl HudPosition {
+    pub fn new(anchor: Anchor, x: f32, y: f32) -> Self {
+        Self {
+            anchor,
+            offset: [x, y],
+        }
+    }
+    // The top left corner of an element of the given size on a screen of the given size
+    pub fn place(&self, size: (f32, f32), screen: (f32, f32)) -> (f32, f32) {
+        let [x, y] = self.offset;
+        match self.anchor {
+            Anchor::TopLeft => (x, y),
+            Anchor::TopRight => (screen.0 - size.0 - x, y),
+            Anchor::BottomLeft => (x, screen.1 - size.1 - y),
+            Anchor::BottomRight => (screen.0 - size.0 - x, screen.1 - size.1 - y),
+            Anchor::Center => ((screen.0 - size.0) / 2.0 + x, (screen.1 - size.1) / 2.0 + y),
+        }
+    }
+}
+
+pub struct HudLayout {
+    pub visible: bool,
+    // Screen pixels per font pixel
+    pub scale: f32,
+    pub color: [f32; 4],
+    // Used for the health when it runs low
+    pub warning_color: [f32; 4],
+    pub warning_health: f32,
+    pub speed: HudPosition,
+    pub thrust: HudPosition,
+    pub health: HudPosition,
+    pub target: HudPosition,
+    pub weapon: HudPosition,
+    pub score: HudPosition,
+    pub game_over: HudPosition,
+    pub crosshair: HudPosition,
+    // 0 hides the crosshair
+    pub crosshair_size: f32,
+}
+
+impl Default for HudLayout {
+    fn default() -> HudLayout {
+        HudLayout {
+            visible: true,
+            scale: 2.0,
+            color: [0.6, 1.0, 0.6, 0.9],
+            warning_color: [1.0, 0.3, 0.2, 0.9],
+            warning_health: 25.0,
+            speed: HudPosition::new(Anchor::BottomLeft, 20.0, 20.0),
+            thrust: HudPosition::new(Anchor::BottomRight, 20.0, 20.0),
+            health: HudPosition::new(Anchor::TopLeft, 20.0, 20.0),
+            target: HudPosition::new(Anchor::TopRight, 20.0, 20.0),
+            weapon: HudPosition::new(Anchor::BottomRight, 20.0, 50.0),
+            score: HudPosition::new(Anchor::TopRight, 20.0, 80.0),
+            game_over: HudPosition::new(Anchor::Center, 0.0, -50.0),
+            crosshair: HudPosition::new(Anchor::Center, 0.0, 0.0),
+            crosshair_size: 12.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HudTarget {
+    pub health: f32,
+    pub distance: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HudWeapon {
+    pub name: String,
+    pub ammo: u32,
+    // 0 never runs out
+    pub magazine: u32,
+}
+
+// What the HUD shows about the player, computed every tick by the HudSystem
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Hud {
+    pub speed: f32,
+    pub thrust: f32,
+    pub health: Option<f32>,
+    pub shield: Option<f32>,
+    // Respawns left, None when the player has no Lives
+    pub lives: Option<u32>,
+    pub target: Option<HudTarget>,
+    pub weapon: Option<HudWeapon>,
+    pub kills: u32,
+    pub game_over: bool,
+}
+
+impl Hud {
+    pub fn speed_text(&self) -> String {
+        format!("SPEED {:.1}", self.speed)
+    }
+    pub fn thrust_text(&self) -> String {
+        format!("THRUST {:.1}", self.thrust)
+    }
+    pub fn health_text(&self) -> String {
+        let health = match self.health {
+            Some(health) => format!("HEALTH {:.0}", health.max(0.0)),
+            None => "HEALTH -".to_owned(),
+        };
+        let health = match self.shield {
+            Some(shield) => format!("{}\nSHIELD {:.0}", health, shield),
+            None => health,
+        };
+        match self.lives {
+            Some(lives) => format!("{}\nLIVES {}", health, lives),
+            None => health,
+        }
+    }
+    pub fn target_text(&self) -> String {
+        match &self.target {
+            Some(target) => format!(
+                "TARGET {:.0} HP\n{:.0} M",
+                target.health.max(0.0),
+                target.distance
+            ),
+            None => "NO TARGET".to_owned(),
+        }
+    }
+    pub fn game_over_text(&self) -> String {
+        format!("GAME OVER\nKILLS {}", self.kills)
+    }
+    pub fn score_text(&self) -> String {
+        format!("KILLS {}", self.kills)
+    }
+    pub fn weapon_text(&self) -> String {
+        match &self.weapon {
+            Some(weapon) if weapon.magazine == 0 => weapon.name.to_uppercase(),
+            Some(weapon) if weapon.ammo == 0 => format!("{} RELOADING", weapon.name.to_uppercase()),
+            Some(weapon) => format!(
+                "{} {}/{}",
+                weapon.name.to_uppercase(),
+                weapon.ammo,
+                weapon.magazine
+            ),
+            None => "NO WEAPON".to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: (f32, f32) = (800.0, 600.0);
+    const SIZE: (f32, f32) = (100.0, 20.0);
+
+    fn place(anchor: Anchor, x: f32, y: f32) -> (f32, f32) {
+        HudPosition::new(anchor, x, y).place(SIZE, SCREEN)
+    }
+
+    #[test]
+    fn corners_offset_towards_the_middle() {
+        assert_eq!(place(Anchor::TopLeft, 10.0, 5.0), (10.0, 5.0));
+        assert_eq!(place(Anchor::TopRight, 10.0, 5.0), (690.0, 5.0));
+        assert_eq!(place(Anchor::BottomLeft, 10.0, 5.0), (10.0, 575.0));
+        assert_eq!(place(Anchor::BottomRight, 10.0, 5.0), (690.0, 575.0));
+    }
+
+    #[test]
+    fn center_offsets_from_the_middle() {
+        assert_eq!(place(Anchor::Center, 0.0, 0.0), (350.0, 290.0));
+        assert_eq!(place(Anchor::Center, 10.0, -50.0), (360.0, 240.0));
+    }
+
+    #[test]
+    fn speed_and_thrust() {
+        let hud = Hud {
+            speed: 12.345,
+            thrust: -0.26,
+            ..Hud::default()
+        };
+        assert_eq!(hud.speed_text(), "SPEED 12.3");
+        assert_eq!(hud.thrust_text(), "THRUST -0.3");
+    }
+
+    #[test]
+    fn health_with_shield_and_lives() {
+        let mut hud = Hud::default();
+        assert_eq!(hud.health_text(), "HEALTH -");
+        hud.health = Some(-5.0);
+        assert_eq!(hud.health_text(), "HEALTH 0");
+        hud.health = Some(42.4);
+        hud.shield = Some(9.6);
+        assert_eq!(hud.health_text(), "HEALTH 42\nSHIELD 10");
+        hud.lives = Some(2);
+        assert_eq!(hud.health_text(), "HEALTH 42\nSHIELD 10\nLIVES 2");
+        hud.shield = None;
+        assert_eq!(hud.health_text(), "HEALTH 42\nLIVES 2");
+    }
+
+    #[test]
+    fn target() {
+        let mut hud = Hud::default();
+        assert_eq!(hud.target_text(), "NO TARGET");
+        hud.target = Some(HudTarget {
+            health: -3.0,
+            distance: 1234.4,
+        });
+        assert_eq!(hud.target_text(), "TARGET 0 HP\n1234 M");
+    }
+
+    #[test]
+    fn weapon() {
+        let mut hud = Hud::default();
+        assert_eq!(hud.weapon_text(), "NO WEAPON");
+        hud.weapon = Some(HudWeapon {
+            name: "laser".to_owned(),
+            ammo: 7,
+            magazine: 30,
+        });
+        assert_eq!(hud.weapon_text(), "LASER 7/30");
+        hud.weapon.as_mut().unwrap().ammo = 0;
+        assert_eq!(hud.weapon_text(), "LASER RELOADING");
+        hud.weapon.as_mut().unwrap().magazine = 0;
+        assert_eq!(hud.weapon_text(), "LASER");
+    }
+
+    #[test]
+    fn kills() {
+        let hud = Hud {
+            kills: 3,
+            game_over: true,
+            ..Hud::default()
+        };
+        assert_eq!(hud.score_text(), "KILLS 3");
+        assert_eq!(hud.game_over_text(), "GAME OVER\nKILLS 3");
+    }
+}
@@ -1,2 +1,17 @@
+pub mod asset_stats;
+pub mod background;
 pub mod collisions;
+pub mod console;
+pub mod debug_draw;
+pub mod destructions;
 pub mod doem_events;
+pub mod frame_check;
+pub mod game_state;
+pub mod hud;
+pub mod overlay;
+pub mod particles;
+pub mod preload;
+pub mod profiler;
+pub mod render_stats;
+pub mod score;
+pub mod time_scale;
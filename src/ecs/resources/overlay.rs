@@ -0,0 +1,60 @@
+use crate::bitmap_font;
+
+// A filled rectangle in screen pixels, the origin is the top left corner of the window
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub color: [f32; 4],
+}
+
+// 2D shapes and text drawn on top of the scene, any system can push into it during a tick.
+// The GLSystem draws and clears it every frame, and keeps the window size up to date.
+#[derive(Default)]
+pub struct Overlay {
+    pub width: f32,
+    pub height: f32,
+    pub rects: Vec<Rect>,
+}
+
+impl Overlay {
+    pub fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: [f32; 4]) {
+        self.rects.push(Rect {
+            x,
+            y,
+            width,
+            height,
+            color,
+        });
+    }
+    // Every font pixel becomes scale by scale screen pixels
+    pub fn text(&mut self, text: &str, x: f32, y: f32, scale: f32, color: [f32; 4]) {
+        for (line_index, line) in text.split('\n').enumerate() {
+            let line_y = y + (line_index * bitmap_font::LINE_HEIGHT) as f32 * scale;
+            for (char_index, c) in line.chars().enumerate() {
+                let glyph_x = x + (char_index * bitmap_font::ADVANCE) as f32 * scale;
+                for (row_index, row) in bitmap_font::glyph(c).iter().enumerate() {
+                    for (column, length) in bitmap_font::runs(*row) {
+                        self.rect(
+                            glyph_x + column as f32 * scale,
+                            line_y + row_index as f32 * scale,
+                            length as f32 * scale,
+                            scale,
+                            color,
+                        );
+                    }
+                }
+            }
+        }
+    }
+    // The size of a text drawn with the given scale, in screen pixels
+    pub fn text_size(text: &str, scale: f32) -> (f32, f32) {
+        let (width, height) = bitmap_font::text_size(text);
+        (width as f32 * scale, height as f32 * scale)
+    }
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+}
@@ -0,0 +1,98 @@
+use crate::ecs::components::particle_emitter::ParticleEmitter;
+use crate::random::Random;
+use doem_math::{Matrix4, Vector3};
+
+const MAX_PARTICLES: usize = 20_000;
+pub const DEFAULT_SEED: u64 = 0x5eed;
+
+pub struct Particle {
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub age: f32,
+    pub lifetime: u32,
+    pub start_color: [f32; 4],
+    pub end_color: [f32; 4],
+    pub start_size: f32,
+    pub end_size: f32,
+}
+
+impl Particle {
+    fn life(&self) -> f32 {
+        self.age / self.lifetime as f32
+    }
+    pub fn color(&self) -> [f32; 4] {
+        let life = self.life();
+        let mut color = [0.0; 4];
+        for (i, c) in color.iter_mut().enumerate() {
+            *c = self.start_color[i] + (self.end_color[i] - self.start_color[i]) * life;
+        }
+        color
+    }
+    pub fn size(&self) -> f32 {
+        self.start_size + (self.end_size - self.start_size) * self.life()
+    }
+}
+
+// Every living particle, moved by the ParticleSystem and drawn by the GLSystem
+pub struct Particles {
+    pub particles: Vec<Particle>,
+    random: Random,
+}
+
+impl Default for Particles {
+    fn default() -> Particles {
+        Particles::new(DEFAULT_SEED)
+    }
+}
+
+impl Particles {
+    pub fn new(seed: u64) -> Particles {
+        Particles {
+            particles: Vec::<Particle>::new(),
+            random: Random::new(seed),
+        }
+    }
+    pub fn emit(
+        &mut self,
+        emitter: &ParticleEmitter,
+        count: usize,
+        position: &Vector3,
+        orientation: &Matrix4,
+    ) {
+        let count = count.min(MAX_PARTICLES - self.particles.len().min(MAX_PARTICLES));
+        let origin = position + &(orientation * &emitter.offset.dimension_hop()).dimension_hop();
+        let velocity: Vector3 = (orientation * &emitter.velocity.dimension_hop()).dimension_hop();
+        let spread = emitter.velocity_spread;
+        for _ in 0..count {
+            let mut particle_velocity = [0.0; 3];
+            for (i, v) in particle_velocity.iter_mut().enumerate() {
+                *v = velocity[i][0] + self.random.range(-spread, spread);
+            }
+            self.particles.push(Particle {
+                position: [origin[0][0], origin[1][0], origin[2][0]],
+                velocity: particle_velocity,
+                age: 0.0,
+                lifetime: emitter.lifetime.max(1),
+                start_color: emitter.start_color,
+                end_color: emitter.end_color,
+                start_size: emitter.start_size,
+                end_size: emitter.end_size,
+            });
+        }
+    }
+    // For one-off effects like explosions, which don't need an entity of their own
+    pub fn burst(&mut self, emitter: &ParticleEmitter, position: &Vector3, orientation: &Matrix4) {
+        self.emit(emitter, emitter.burst, position, orientation);
+    }
+    // Moves the particles a tick times the time scale further and removes the ones which lived
+    // their lifetime
+    pub fn update(&mut self, time_scale: f32) {
+        for p in self.particles.iter_mut() {
+            for i in 0..3 {
+                p.position[i] += p.velocity[i] * time_scale;
+            }
+            p.age += time_scale;
+        }
+        self.particles.retain(|p| p.age < p.lifetime as f32);
+    }
+}
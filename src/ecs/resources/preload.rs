@@ -0,0 +1,8 @@
+// Obj paths the TessManager should start loading before any Shape asks for them
+pub struct Preload(pub Vec<String>);
+
+impl Default for Preload {
+    fn default() -> Preload {
+        Preload(Vec::<String>::new())
+    }
+}
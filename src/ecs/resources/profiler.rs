@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+// The statistics are taken over this many frames
+pub const HISTORY: usize = 240;
+// The time between two calls of end_frame, next to the systems
+pub const FRAME: &str = "frame";
+
+pub struct Timing {
+    pub name: &'static str,
+    pub thread: ThreadId,
+    // Since the profiler was created
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+// Shared with the systems, so they can time themselves while they run in parallel
+#[derive(Clone)]
+pub struct Recorder {
+    epoch: Instant,
+    timings: Arc<Mutex<Vec<Timing>>>,
+}
+
+impl Recorder {
+    pub fn scope(&self, name: &'static str) -> Scope<'_> {
+        Scope {
+            recorder: self,
+            name,
+            start: Instant::now(),
+        }
+    }
+}
+
+// Records how long it lived when it is dropped
+pub struct Scope<'a> {
+    recorder: &'a Recorder,
+    name: &'static str,
+    start: Instant,
+}
+
+impl<'a> Drop for Scope<'a> {
+    fn drop(&mut self) {
+        let timing = Timing {
+            name: self.name,
+            thread: thread::current().id(),
+            start: self.start.duration_since(self.recorder.epoch),
+            duration: self.start.elapsed(),
+        };
+        if let Ok(mut timings) = self.recorder.timings.lock() {
+            timings.push(timing);
+        }
+    }
+}
+
+// In milliseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+    pub p99: f32,
+}
+
+impl Stats {
+    fn from_samples(samples: &VecDeque<f32>) -> Stats {
+        if samples.is_empty() {
+            return Stats::default();
+        }
+        let mut sorted: Vec<f32> = samples.iter().cloned().collect();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let p99 = ((sorted.len() as f32 * 0.99).ceil() as usize).max(1) - 1;
+        Stats {
+            min: sorted[0],
+            avg: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            max: sorted[sorted.len() - 1],
+            p99: sorted[p99],
+        }
+    }
+}
+
+// Collects the timings of every system per frame, see Recorder
+pub struct Profiler {
+    // Shows the statistics and the frame graph
+    pub visible: bool,
+    recorder: Recorder,
+    last_frame: Instant,
+    // Milliseconds per frame, the newest at the back
+    history: BTreeMap<&'static str, VecDeque<f32>>,
+    // Every timing since start_trace was called
+    trace: Option<Vec<Timing>>,
+}
+
+impl Default for Profiler {
+    fn default() -> Profiler {
+        Profiler {
+            visible: false,
+            recorder: Recorder {
+                epoch: Instant::now(),
+                timings: Arc::new(Mutex::new(Vec::<Timing>::new())),
+            },
+            last_frame: Instant::now(),
+            history: BTreeMap::new(),
+            trace: None,
+        }
+    }
+}
+
+impl Profiler {
+    pub fn recorder(&self) -> Recorder {
+        self.recorder.clone()
+    }
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Vec::new());
+    }
+    // Called once after every dispatch
+    pub fn end_frame(&mut self) {
+        let timings: Vec<Timing> = match self.recorder.timings.lock() {
+            Ok(mut timings) => timings.drain(..).collect(),
+            Err(_) => return,
+        };
+        let mut totals: BTreeMap<&'static str, f32> = BTreeMap::new();
+        for timing in &timings {
+            *totals.entry(timing.name).or_insert(0.0) += Self::millis(timing.duration);
+        }
+        totals.insert(FRAME, Self::millis(self.last_frame.elapsed()));
+        self.last_frame = Instant::now();
+
+        // Something which did not run this frame took no time
+        for name in self.history.keys() {
+            totals.entry(*name).or_insert(0.0);
+        }
+        for (name, millis) in totals {
+            let samples = self.history.entry(name).or_insert_with(VecDeque::new);
+            samples.push_back(millis);
+            if samples.len() > HISTORY {
+                samples.pop_front();
+            }
+        }
+        if let Some(trace) = &mut self.trace {
+            trace.extend(timings);
+        }
+    }
+    pub fn names(&self) -> Vec<&'static str> {
+        self.history.keys().cloned().collect()
+    }
+    pub fn stats(&self, name: &str) -> Stats {
+        self.history
+            .get(name)
+            .map(Stats::from_samples)
+            .unwrap_or_default()
+    }
+    pub fn samples(&self, name: &str) -> Option<&VecDeque<f32>> {
+        self.history.get(name)
+    }
+    // In the Chrome trace event format, which chrome://tracing and Perfetto can open
+    pub fn write_trace<P>(&self, path: P) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let trace = match &self.trace {
+            Some(trace) => trace,
+            None => return Ok(()),
+        };
+        let mut threads: HashMap<ThreadId, usize> = HashMap::new();
+        let mut events: Vec<String> = Vec::with_capacity(trace.len());
+        for timing in trace {
+            let next_thread = threads.len();
+            let thread = *threads.entry(timing.thread).or_insert(next_thread);
+            events.push(format!(
+                "{{\"name\":\"{}\",\"ph\":\"X\",\"pid\":1,\"tid\":{},\"ts\":{},\"dur\":{}}}",
+                timing.name,
+                thread,
+                timing.start.as_micros(),
+                timing.duration.as_micros()
+            ));
+        }
+        fs::write(
+            path,
+            format!("{{\"traceEvents\":[\n{}\n]}}\n", events.join(",\n")),
+        )
+    }
+    fn millis(duration: Duration) -> f32 {
+        duration.as_secs_f32() * 1000.0
+    }
+}
@@ -0,0 +1,6 @@
+// Written by the GLSystem after every frame
+#[derive(Default)]
+pub struct RenderStats {
+    pub drawn_entities: usize,
+    pub culled_entities: usize,
+}
@@ -0,0 +1,5 @@
+// What the player destroyed
+#[derive(Default)]
+pub struct Score {
+    pub kills: u32,
+}
@@ -0,0 +1,8 @@
+// How fast the world moves compared to normal, set from the console. 0 pauses it.
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> TimeScale {
+        TimeScale(1.0)
+    }
+}
@@ -0,0 +1,299 @@
+use crate::ecs::components::ai_pilot::{AiPilot, Steering};
+use crate::ecs::components::camera::Camera;
+use crate::ecs::components::physics::Physics;
+use crate::ecs::components::thruster::Thruster;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::debug_draw::{self, DebugDraw};
+use crate::ecs::resources::time_scale::TimeScale;
+use doem_math::{Matrix4, Vector3, Vector4};
+use specs::prelude::*;
+use std::f32::consts::FRAC_1_SQRT_2;
+
+// Pursuit does not look further ahead than this many ticks
+const MAX_PREDICTION: f32 = 120.0;
+// How much the speed changes per tick for pilots without a Thruster
+const DEFAULT_THRUST: f32 = 0.1;
+const ARRIVED_DISTANCE: f32 = 1.0;
+const STEERING_ARROW_LENGTH: f32 = 100.0;
+
+pub struct AiSystem;
+
+impl AiSystem {
+    fn rotate(orientation: &Matrix4, v: &Vector3) -> Vector3 {
+        (orientation * &Vector4::from([[v[0][0]], [v[1][0]], [v[2][0]], [0.0]])).dimension_hop()
+    }
+    // The ships fly along their negative X axis, like the PhysicsSystem moves them
+    pub fn forward(orientation: &Matrix4) -> Vector3 {
+        Self::rotate(orientation, &Vector3::from([[-1.0], [0.0], [0.0]]))
+    }
+    // Physics keeps the velocity relative to the orientation, and moves along the normalized
+    // homogeneous axes, so every component ends up divided by the square root of two
+    pub fn to_world(orientation: &Matrix4, velocity: &Vector3) -> Vector3 {
+        let local = Vector3::from([[-velocity[0][0]], [velocity[1][0]], [velocity[2][0]]]);
+        &Self::rotate(orientation, &local) * FRAC_1_SQRT_2
+    }
+    pub fn world_velocity(t: &Transform, p: &Physics) -> Vector3 {
+        Self::to_world(&t.orientation, &p.velocity)
+    }
+    // The direction and speed the pilot wants to fly at, None when it is where it wants to be
+    fn steer(
+        pilot: &AiPilot,
+        position: &Vector3,
+        target: &Vector3,
+        target_velocity: &Vector3,
+    ) -> Option<(Vector3, f32)> {
+        let to_target = target - position;
+        let distance = to_target.length();
+        if distance < ARRIVED_DISTANCE {
+            return None;
+        }
+        let direction = &to_target * (1.0 / distance);
+        match pilot.steering {
+            Steering::Seek => Some((direction, pilot.max_speed)),
+            Steering::Flee => Some((&direction * -1.0, pilot.max_speed)),
+            Steering::Pursue => {
+                let ticks = (distance / (pilot.max_speed * FRAC_1_SQRT_2)).min(MAX_PREDICTION);
+                let predicted = target + &(target_velocity * ticks);
+                let to_predicted = &predicted - position;
+                if to_predicted.length() < ARRIVED_DISTANCE {
+                    return None;
+                }
+                Some((to_predicted.normalize(), pilot.max_speed))
+            }
+            Steering::Arrive => {
+                let speed = pilot.max_speed * (distance / pilot.slowing_radius).min(1.0);
+                Some((direction, speed))
+            }
+            Steering::Orbit { radius } => {
+                let mut tangent = direction.cross_product(&Vector3::from([[0.0], [1.0], [0.0]]));
+                if tangent.length() < 0.001 {
+                    tangent = direction.cross_product(&Vector3::from([[1.0], [0.0], [0.0]]));
+                }
+                // Going around while steering back onto the circle
+                let correction = &direction * ((distance - radius) / radius);
+                let heading = (&tangent.normalize() + &correction).normalize();
+                Some((heading, pilot.max_speed))
+            }
+        }
+    }
+    // Turns the nose at most max_angle towards the direction
+    pub fn turn(orientation: &Matrix4, direction: &Vector3, max_angle: f32) -> Matrix4 {
+        let forward = Self::forward(orientation);
+        let cos = forward.dot_product(direction).max(-1.0).min(1.0);
+        let angle = cos.acos();
+        if angle < 0.0001 {
+            return orientation.clone();
+        }
+        let mut axis = forward.cross_product(direction);
+        if axis.length() < 0.0001 {
+            // Facing straight away, turning around the up axis will do
+            axis = Self::rotate(orientation, &Vector3::from([[0.0], [1.0], [0.0]]));
+        }
+        let axis: Vector4 = axis.normalize().dimension_hop();
+        let step = angle.min(max_angle);
+        // Take whichever way around gets the nose closer
+        let turned = &Matrix4::get_rotation(&axis, step) * orientation;
+        if Self::forward(&turned).dot_product(direction) >= cos {
+            turned
+        } else {
+            &Matrix4::get_rotation(&axis, -step) * orientation
+        }
+    }
+}
+
+impl<'a> System<'a> for AiSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, TimeScale>,
+        Write<'a, DebugDraw>,
+        ReadStorage<'a, AiPilot>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Thruster>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Physics>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            time_scale,
+            mut debug_draw,
+            ai_pilot,
+            camera,
+            thruster,
+            mut transform,
+            mut physics,
+        ): Self::SystemData,
+    ) {
+        let player = (&*entities, &camera).join().next().map(|(e, _)| e);
+
+        // Decided up front, the targets can be pilots themselves
+        let mut steerings: Vec<(Entity, Option<(Vector3, f32)>)> = Vec::new();
+        for (e, pilot, t) in (&*entities, &ai_pilot, &transform).join() {
+            let target = pilot.target.or(player).filter(|target| *target != e);
+            let steering = target.and_then(|target| {
+                let target_t = transform.get(target)?;
+                let target_velocity = match physics.get(target) {
+                    Some(p) => Self::world_velocity(target_t, p),
+                    None => Vector3::origin(),
+                };
+                Self::steer(pilot, &t.position, &target_t.position, &target_velocity)
+            });
+            steerings.push((e, steering));
+        }
+
+        for (e, steering) in steerings {
+            let pilot = ai_pilot.get(e).unwrap();
+            let thrust = thruster.get(e).map_or(DEFAULT_THRUST, |t| t.power.length());
+            let (t, p) = match (transform.get_mut(e), physics.get_mut(e)) {
+                (Some(t), Some(p)) => (t, p),
+                _ => continue,
+            };
+            let desired_speed = match &steering {
+                Some((direction, speed)) => {
+                    t.orientation =
+                        Self::turn(&t.orientation, direction, pilot.turn_rate * time_scale.0);
+                    debug_draw.arrow(
+                        &t.position,
+                        &(direction * STEERING_ARROW_LENGTH),
+                        debug_draw::GREEN,
+                    );
+                    // Only full thrust once the nose points the right way
+                    let alignment = Self::forward(&t.orientation).dot_product(direction);
+                    speed * (alignment + 1.0) / 2.0
+                }
+                None => 0.0,
+            };
+            let speed = p.velocity[0][0];
+            let max_change = thrust * time_scale.0;
+            let speed = speed + (desired_speed - speed).max(-max_change).min(max_change);
+            p.velocity = Vector3::from([[speed], [0.0], [0.0]]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vector(x: f32, y: f32, z: f32) -> Vector3 {
+        Vector3::from([[x], [y], [z]])
+    }
+
+    fn assert_close(a: &Vector3, b: &Vector3) {
+        for i in 0..3 {
+            assert!(
+                (a[i][0] - b[i][0]).abs() < 0.0001,
+                "{} is not {}",
+                a[i][0],
+                b[i][0]
+            );
+        }
+    }
+
+    fn steer(steering: Steering, target: &Vector3, target_velocity: &Vector3) -> (Vector3, f32) {
+        let pilot = AiPilot::new(steering);
+        AiSystem::steer(&pilot, &Vector3::origin(), target, target_velocity).unwrap()
+    }
+
+    #[test]
+    fn seek_and_flee() {
+        let target = vector(0.0, 0.0, 500.0);
+        let (direction, speed) = steer(Steering::Seek, &target, &Vector3::origin());
+        assert_close(&direction, &vector(0.0, 0.0, 1.0));
+        assert_eq!(speed, 6.0);
+        let (direction, speed) = steer(Steering::Flee, &target, &Vector3::origin());
+        assert_close(&direction, &vector(0.0, 0.0, -1.0));
+        assert_eq!(speed, 6.0);
+    }
+
+    #[test]
+    fn arrive_slows_down_inside_the_slowing_radius() {
+        let (_, speed) = steer(
+            Steering::Arrive,
+            &vector(600.0, 0.0, 0.0),
+            &Vector3::origin(),
+        );
+        assert_eq!(speed, 6.0);
+        let (direction, speed) = steer(
+            Steering::Arrive,
+            &vector(150.0, 0.0, 0.0),
+            &Vector3::origin(),
+        );
+        assert_close(&direction, &vector(1.0, 0.0, 0.0));
+        assert!((speed - 3.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn nothing_to_do_at_the_target() {
+        let pilot = AiPilot::new(Steering::Seek);
+        let position = vector(10.0, 10.0, 10.0);
+        let target = vector(10.5, 10.0, 10.0);
+        assert!(AiSystem::steer(&pilot, &position, &target, &Vector3::origin()).is_none());
+    }
+
+    #[test]
+    fn pursue_leads_a_moving_target() {
+        let (direction, _) = steer(
+            Steering::Pursue,
+            &vector(400.0, 0.0, 0.0),
+            &vector(0.0, 0.0, 2.0),
+        );
+        assert!(direction[0][0] > 0.0 && direction[2][0] > 0.0);
+        assert!((direction.length() - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn orbit_goes_around_on_the_circle() {
+        let target = vector(400.0, 0.0, 0.0);
+        let (direction, _) = steer(
+            Steering::Orbit { radius: 400.0 },
+            &target,
+            &Vector3::origin(),
+        );
+        assert!(direction.dot_product(&vector(1.0, 0.0, 0.0)).abs() < 0.0001);
+        // Too far out it heads back in
+        let (direction, _) = steer(
+            Steering::Orbit { radius: 100.0 },
+            &target,
+            &Vector3::origin(),
+        );
+        assert!(direction[0][0] > 0.0);
+    }
+
+    #[test]
+    fn turn_is_limited_by_the_turn_rate() {
+        let orientation = Matrix4::identity();
+        let direction = vector(0.0, 0.0, 1.0);
+        let turned = AiSystem::turn(&orientation, &direction, 0.1);
+        let forward = AiSystem::forward(&turned);
+        let before = AiSystem::forward(&orientation);
+        assert!((forward.dot_product(&before) - 0.1f32.cos()).abs() < 0.0001);
+        assert!(forward.dot_product(&direction) > 0.0);
+    }
+
+    #[test]
+    fn turn_stops_at_the_direction() {
+        let direction = vector(0.0, 1.0, -1.0).normalize();
+        let turned = AiSystem::turn(&Matrix4::identity(), &direction, 3.0);
+        assert_close(&AiSystem::forward(&turned), &direction);
+        let again = AiSystem::turn(&turned, &direction, 3.0);
+        assert_close(&AiSystem::forward(&again), &direction);
+    }
+
+    #[test]
+    fn turn_around_when_facing_away() {
+        let direction = vector(1.0, 0.0, 0.0);
+        let turned = AiSystem::turn(&Matrix4::identity(), &direction, 0.5);
+        let forward = AiSystem::forward(&turned);
+        assert!(forward.dot_product(&direction) > -1.0 + 0.1);
+    }
+
+    #[test]
+    fn world_velocity_follows_the_nose() {
+        let velocity = vector(2.0, 0.0, 0.0);
+        let world = AiSystem::to_world(&Matrix4::identity(), &velocity);
+        assert_close(&world, &vector(-2.0 * FRAC_1_SQRT_2, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,175 @@
+use crate::ecs::components::ai_pilot::AiPilot;
+use crate::ecs::components::camera::Camera;
+use crate::ecs::components::collider::Collider;
+use crate::ecs::components::damage::Damage;
+use crate::ecs::components::fire_intent::FireIntent;
+use crate::ecs::components::gun::Gun;
+use crate::ecs::components::physics::Physics;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::debug_draw::{self, DebugDraw};
+use crate::ecs::systems::ai_system::AiSystem;
+use doem_math::Vector3;
+use specs::prelude::*;
+
+// Pilots do not fire at targets further away than this, or than the range of their weapon
+const FIRE_RANGE: f32 = 1500.0;
+// How far off the nose the lead point may be, in radians
+const AIM_TOLERANCE: f32 = 0.05;
+
+// Makes the pilots fire their Gun when their target is in front of them, taking into account
+// where the target will be when the bullet gets there
+pub struct AiTargetingSystem;
+
+impl AiTargetingSystem {
+    // Where a bullet at the given speed meets the target, relative to the shooter. None when
+    // the target outruns the bullets.
+    fn lead(offset: &Vector3, velocity: &Vector3, bullet_speed: f32) -> Option<Vector3> {
+        let a = velocity.dot_product(velocity) - bullet_speed * bullet_speed;
+        let b = 2.0 * offset.dot_product(velocity);
+        let c = offset.dot_product(offset);
+        let ticks = if a.abs() < 0.0001 {
+            // As fast as the bullets, it can only be hit while coming closer
+            if b >= 0.0 {
+                return None;
+            }
+            -c / b
+        } else {
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant < 0.0 {
+                return None;
+            }
+            let root = discriminant.sqrt();
+            let first = (-b - root) / (2.0 * a);
+            let second = (-b + root) / (2.0 * a);
+            if first > 0.0 && second > 0.0 {
+                first.min(second)
+            } else {
+                first.max(second)
+            }
+        };
+        if ticks <= 0.0 {
+            return None;
+        }
+        Some(offset + &(velocity * ticks))
+    }
+    // Whether none of the spheres is in the way from the start to the end
+    fn line_of_sight(from: &Vector3, to: &Vector3, obstacles: &[(Vector3, f32)]) -> bool {
+        let line = to - from;
+        let length_squared = line.dot_product(&line);
+        obstacles.iter().all(|(center, radius)| {
+            let along = if length_squared > 0.0 {
+                ((center - from).dot_product(&line) / length_squared)
+                    .max(0.0)
+                    .min(1.0)
+            } else {
+                0.0
+            };
+            let closest = from + &(&line * along);
+            (center - &closest).length() > *radius
+        })
+    }
+}
+
+impl<'a> System<'a> for AiTargetingSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, DebugDraw>,
+        ReadStorage<'a, AiPilot>,
+        WriteStorage<'a, FireIntent>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Gun>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Physics>,
+        ReadStorage<'a, Collider>,
+        ReadStorage<'a, Damage>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut debug_draw,
+            ai_pilot,
+            mut fire_intent,
+            camera,
+            gun,
+            transform,
+            physics,
+            collider,
+            damage,
+        ): Self::SystemData,
+    ) {
+        let player = (&*entities, &camera).join().next().map(|(e, _)| e);
+        // Bullets do not block the view
+        let obstacles: Vec<(Entity, Vector3, f32)> = (&*entities, &transform, &collider, !&damage)
+            .join()
+            .map(|(e, t, c, _)| {
+                let radius = (0..3)
+                    .map(|i| (c.half_size[i][0] * t.scale[i][0]).abs())
+                    .fold(0.0, f32::max);
+                (e, t.position.clone(), radius)
+            })
+            .collect();
+
+        for (e, pilot, intent, g, t, p) in (
+            &*entities,
+            &ai_pilot,
+            &mut fire_intent,
+            &gun,
+            &transform,
+            &physics,
+        )
+            .join()
+        {
+            if !g.ready() {
+                continue;
+            }
+            let target = match pilot.target.or(player).filter(|target| *target != e) {
+                Some(target) => target,
+                None => continue,
+            };
+            let target_t = match transform.get(target) {
+                Some(target_t) => target_t,
+                None => continue,
+            };
+            let offset = &target_t.position - &t.position;
+            let range = if g.weapon.range > 0.0 {
+                g.weapon.range.min(FIRE_RANGE)
+            } else {
+                FIRE_RANGE
+            };
+            if offset.length() > range {
+                continue;
+            }
+
+            // The bullets keep the velocity of the shooter
+            let target_velocity = match physics.get(target) {
+                Some(target_p) => AiSystem::world_velocity(target_t, target_p),
+                None => Vector3::origin(),
+            };
+            let relative_velocity = &target_velocity - &AiSystem::world_velocity(t, p);
+            let muzzle_velocity = Vector3::from([[g.weapon.speed], [0.0], [0.0]]);
+            let bullet_speed = AiSystem::to_world(&t.orientation, &muzzle_velocity).length();
+            let aim = match Self::lead(&offset, &relative_velocity, bullet_speed) {
+                Some(aim) => aim,
+                None => continue,
+            };
+            let forward = AiSystem::forward(&t.orientation);
+            if forward.dot_product(&aim.normalize()) < AIM_TOLERANCE.cos() {
+                continue;
+            }
+
+            let aim_point = &t.position + &aim;
+            let in_the_way: Vec<(Vector3, f32)> = obstacles
+                .iter()
+                .filter(|(obstacle, ..)| *obstacle != e && *obstacle != target)
+                .map(|(_, center, radius)| (center.clone(), *radius))
+                .collect();
+            if !Self::line_of_sight(&t.position, &aim_point, &in_the_way) {
+                continue;
+            }
+            debug_draw.line(&t.position, &aim_point, debug_draw::RED);
+            intent.firing = true;
+        }
+    }
+}
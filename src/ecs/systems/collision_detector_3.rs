@@ -3,6 +3,7 @@ use crate::ecs::components::collider::Collider;
 use crate::ecs::components::shape::Shape;
 use crate::ecs::components::transform::Transform;
 use crate::ecs::resources::collisions::Collisions;
+use crate::ecs::resources::debug_draw::{self, DebugDraw};
 use doem_math::Matrix4;
 use specs::prelude::*;
 
@@ -15,14 +16,26 @@ impl<'a> System<'a> for CollisionDetector3 {
         ReadStorage<'a, Collider>,
         ReadStorage<'a, Transform>,
         Write<'a, Collisions>,
+        Write<'a, DebugDraw>,
     );
 
     // Sadly you must have a shape right now
-    fn run(&mut self, (entities, shape, collider, transform, mut collisions): Self::SystemData) {
+    fn run(
+        &mut self,
+        (entities, shape, collider, transform, mut collisions, mut debug_draw): Self::SystemData,
+    ) {
         collisions.0.clear();
+        if debug_draw.enabled {
+            for (s, _c, t) in (&shape, &collider, &transform).join() {
+                if let Shape::Init { bounding_box, .. } = s {
+                    Self::draw_collider(&mut debug_draw, bounding_box, t);
+                }
+            }
+        }
         for (ent1, s1, _c1, t1) in (&*entities, &shape, &collider, &transform).join() {
             for (ent2, s2, _c2, t2) in (&*entities, &shape, &collider, &transform).join() {
-                if ent1.id() == ent2.id() {
+                // Every pair once, the DamageSystem lets both hit each other
+                if ent1.id() >= ent2.id() {
                     continue;
                 }
                 let bb1: &AABB;
@@ -33,6 +46,8 @@ impl<'a> System<'a> for CollisionDetector3 {
                         bb2 = bounding_box;
                         if Self::intersects(bb1, t1, bb2, t2) {
                             collisions.0.insert((ent1, ent2));
+                            let contact = &(&t1.position + &t2.position) * 0.5;
+                            debug_draw.contact(&contact, debug_draw::RED);
                         }
                     }
                 }
@@ -46,6 +61,18 @@ impl<'a> System<'a> for CollisionDetector3 {
 }
 
 impl CollisionDetector3 {
+    // The rotated box of the shape and the axis aligned box the collisions are tested with
+    fn draw_collider(debug_draw: &mut DebugDraw, bounding_box: &AABB, t: &Transform) {
+        let scaling = Matrix4::get_scaling(&t.scale);
+        let model = &Matrix4::get_translation(&t.position) * &(&t.orientation * &scaling);
+        debug_draw.obb(bounding_box, &model, debug_draw::BLUE);
+        let tested = AABB {
+            middle_point: &(&scaling * &bounding_box.middle_point.dimension_hop()).dimension_hop()
+                + &t.position,
+            half_size: (&scaling * &bounding_box.half_size.dimension_hop()).dimension_hop(),
+        };
+        debug_draw.aabb(&tested, debug_draw::GREEN);
+    }
     pub fn intersects(box1: &AABB, t1: &Transform, box2: &AABB, t2: &Transform) -> bool {
         let box1_scaling = Matrix4::get_scaling(&t1.scale);
         let box2_scaling = Matrix4::get_scaling(&t2.scale);
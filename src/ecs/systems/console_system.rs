@@ -0,0 +1,316 @@
+use crate::bitmap_font;
+use crate::consts;
+use crate::ecs::components::ai_pilot::AiPilot;
+use crate::ecs::components::armor::Armor;
+use crate::ecs::components::camera::Camera;
+use crate::ecs::components::collider::Collider;
+use crate::ecs::components::damage::Damage;
+use crate::ecs::components::despawn::Despawn;
+use crate::ecs::components::fire_intent::FireIntent;
+use crate::ecs::components::gun::Gun;
+use crate::ecs::components::health::Health;
+use crate::ecs::components::homing::Homing;
+use crate::ecs::components::level_of_detail::LevelOfDetail;
+use crate::ecs::components::lifetime::Lifetime;
+use crate::ecs::components::particle_emitter::ParticleEmitter;
+use crate::ecs::components::physics::Physics;
+use crate::ecs::components::pulsate::Pulsate;
+use crate::ecs::components::shape::Shape;
+use crate::ecs::components::shield::Shield;
+use crate::ecs::components::thruster::Thruster;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::components::transformable::Transformable;
+use crate::ecs::resources::console::{Command, Console, Field, HELP};
+use crate::ecs::resources::destructions::Destructions;
+use crate::ecs::resources::overlay::Overlay;
+use crate::ecs::resources::time_scale::TimeScale;
+use doem_math::{Matrix4, Vector3};
+use specs::prelude::*;
+use std::path::Path;
+
+// Screen pixels per font pixel
+const SCALE: f32 = 2.0;
+const MARGIN: f32 = 10.0;
+const VISIBLE_LINES: usize = 16;
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const INSPECTOR_COLOR: [f32; 4] = [0.6, 0.8, 1.0, 1.0];
+const SPAWN_SCALE: f32 = 10.0;
+
+// Runs the commands typed into the Console and draws it and the entity inspector
+pub struct ConsoleSystem;
+
+impl ConsoleSystem {
+    fn vector(values: &[f32]) -> Vector3 {
+        Vector3::from([[values[0]], [values[1]], [values[2]]])
+    }
+    fn format_vector(v: &Vector3) -> String {
+        format!("{:.1} {:.1} {:.1}", v[0][0], v[1][0], v[2][0])
+    }
+    // The living entity with the id, whatever its generation is
+    fn find(entities: &Entities, id: u32) -> Result<Entity, String> {
+        (&**entities)
+            .join()
+            .find(|e| e.id() == id)
+            .ok_or_else(|| format!("there is no entity {}", id))
+    }
+    // A bare name is looked up in the models directory
+    fn obj_path(obj: &str) -> Result<String, String> {
+        let path = if obj.contains('/') {
+            obj.to_owned()
+        } else {
+            format!("{}/{}.obj", consts::MODELS_DIR, obj)
+        };
+        if Path::new(&path).exists() {
+            Ok(path)
+        } else {
+            Err(format!("{} does not exist", path))
+        }
+    }
+    fn panel(overlay: &mut Overlay, lines: &[String], x: f32, y: f32, color: [f32; 4]) -> f32 {
+        let text = lines.join("\n");
+        let (width, height) = Overlay::text_size(&text, SCALE);
+        let x = x.min(overlay.width - width - MARGIN * 2.0).max(0.0);
+        overlay.rect(
+            x,
+            y,
+            width + MARGIN * 2.0,
+            height + MARGIN * 2.0,
+            BACKGROUND_COLOR,
+        );
+        overlay.text(&text, x + MARGIN, y + MARGIN, SCALE, color);
+        height + MARGIN * 2.0
+    }
+}
+
+impl<'a> System<'a> for ConsoleSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Console>,
+        Write<'a, TimeScale>,
+        Write<'a, Destructions>,
+        Write<'a, Overlay>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Physics>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, Shape>,
+        ReadStorage<'a, Collider>,
+        ReadStorage<'a, Transformable>,
+        ReadStorage<'a, Pulsate>,
+        ReadStorage<'a, Damage>,
+        ReadStorage<'a, Gun>,
+        ReadStorage<'a, Thruster>,
+        ReadStorage<'a, Despawn>,
+        ReadStorage<'a, LevelOfDetail>,
+        ReadStorage<'a, ParticleEmitter>,
+        WriteStorage<'a, AiPilot>,
+        ReadStorage<'a, FireIntent>,
+        ReadStorage<'a, Homing>,
+        ReadStorage<'a, Lifetime>,
+        ReadStorage<'a, Shield>,
+        ReadStorage<'a, Armor>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut console,
+            mut time_scale,
+            mut destructions,
+            mut overlay,
+            mut transform,
+            mut physics,
+            mut health,
+            mut camera,
+            mut shape,
+            collider,
+            transformable,
+            pulsate,
+            damage,
+            gun,
+            thruster,
+            despawn,
+            level_of_detail,
+            emitter,
+            mut ai_pilot,
+            fire_intent,
+            homing,
+            lifetime,
+            shield,
+            armor,
+        ): Self::SystemData,
+    ) {
+        let commands: Vec<String> = console.commands.drain(..).collect();
+        for line in commands {
+            let command = match Command::parse(&line) {
+                Ok(command) => command,
+                Err(e) => {
+                    console.print(e);
+                    continue;
+                }
+            };
+            let result: Result<(), String> = match command {
+                Command::Help => {
+                    for help in HELP.iter() {
+                        console.print(*help);
+                    }
+                    Ok(())
+                }
+                Command::List => {
+                    for e in (&*entities).join() {
+                        let mut names: Vec<&str> = Vec::new();
+                        let mut name = |has: bool, n: &'static str| {
+                            if has {
+                                names.push(n)
+                            }
+                        };
+                        name(transform.contains(e), "transform");
+                        name(physics.contains(e), "physics");
+                        name(health.contains(e), "health");
+                        name(camera.contains(e), "camera");
+                        name(shape.contains(e), "shape");
+                        name(collider.contains(e), "collider");
+                        name(transformable.contains(e), "transformable");
+                        name(pulsate.contains(e), "pulsate");
+                        name(damage.contains(e), "damage");
+                        name(gun.contains(e), "gun");
+                        name(thruster.contains(e), "thruster");
+                        name(despawn.contains(e), "despawn");
+                        name(level_of_detail.contains(e), "level of detail");
+                        name(emitter.contains(e), "particle emitter");
+                        name(ai_pilot.contains(e), "ai pilot");
+                        name(fire_intent.contains(e), "fire intent");
+                        name(homing.contains(e), "homing");
+                        name(lifetime.contains(e), "lifetime");
+                        name(shield.contains(e), "shield");
+                        name(armor.contains(e), "armor");
+                        console.print(format!("{}: {}", e.id(), names.join(", ")));
+                    }
+                    Ok(())
+                }
+                Command::Inspect(None) => {
+                    console.inspected = None;
+                    Ok(())
+                }
+                Command::Inspect(Some(id)) => Self::find(&entities, id).map(|e| {
+                    console.inspected = Some(e);
+                }),
+                Command::Set { id, field, values } => Self::find(&entities, id).and_then(|e| {
+                    let missing = |component: &str| format!("{} has no {}", id, component);
+                    match field {
+                        Field::Position => transform
+                            .get_mut(e)
+                            .map(|t| t.position = Self::vector(&values))
+                            .ok_or_else(|| missing("transform")),
+                        Field::Scale => transform
+                            .get_mut(e)
+                            .map(|t| t.scale = Self::vector(&values))
+                            .ok_or_else(|| missing("transform")),
+                        Field::Velocity => physics
+                            .get_mut(e)
+                            .map(|p| p.velocity = Self::vector(&values))
+                            .ok_or_else(|| missing("physics")),
+                        Field::Health => health
+                            .get_mut(e)
+                            .map(|h| h.health = values[0])
+                            .ok_or_else(|| missing("health")),
+                        Field::Zoom => camera
+                            .get_mut(e)
+                            .map(|c| c.zoom_level = values[0])
+                            .ok_or_else(|| missing("camera")),
+                        Field::Offset => camera
+                            .get_mut(e)
+                            .map(|c| c.offset = Self::vector(&values))
+                            .ok_or_else(|| missing("camera")),
+                    }
+                }),
+                Command::Spawn { obj, position } => Self::obj_path(&obj).map(|obj_path| {
+                    let e = entities.create();
+                    shape.insert(e, Shape::Unit { obj_path });
+                    transform.insert(
+                        e,
+                        Transform {
+                            position: Self::vector(&position),
+                            scale: Self::vector(&[SPAWN_SCALE; 3]),
+                            orientation: Matrix4::identity(),
+                        },
+                    );
+                    console.print(format!("spawned {}", e.id()));
+                }),
+                Command::Kill(id) => Self::find(&entities, id).map(|e| {
+                    destructions.destroy(e, None);
+                }),
+                Command::Steer { id, steering } => Self::find(&entities, id).and_then(|e| {
+                    ai_pilot
+                        .get_mut(e)
+                        .map(|p| p.steering = steering)
+                        .ok_or_else(|| format!("{} has no ai pilot", id))
+                }),
+                Command::TimeScale(scale) => {
+                    time_scale.0 = scale;
+                    Ok(())
+                }
+                Command::Clear => {
+                    console.lines.clear();
+                    Ok(())
+                }
+            };
+            if let Err(e) = result {
+                console.print(e);
+            }
+        }
+
+        let mut inspector_y = MARGIN;
+        if console.open {
+            let mut lines: Vec<String> = console
+                .lines
+                .iter()
+                .skip(console.lines.len().saturating_sub(VISIBLE_LINES))
+                .cloned()
+                .collect();
+            lines.push(format!("> {}_", console.input));
+            inspector_y += Self::panel(&mut overlay, &lines, 0.0, 0.0, TEXT_COLOR);
+        }
+
+        let inspected = match console.inspected {
+            Some(e) if entities.is_alive(e) => e,
+            _ => {
+                console.inspected = None;
+                return;
+            }
+        };
+        let mut lines = vec![format!("entity {}", inspected.id())];
+        if let Some(t) = transform.get(inspected) {
+            lines.push(format!("position {}", Self::format_vector(&t.position)));
+            lines.push(format!("scale    {}", Self::format_vector(&t.scale)));
+        }
+        if let Some(p) = physics.get(inspected) {
+            lines.push(format!("velocity {}", Self::format_vector(&p.velocity)));
+        }
+        if let Some(h) = health.get(inspected) {
+            lines.push(format!("health   {:.1}", h.health));
+        }
+        if let Some(s) = shield.get(inspected) {
+            lines.push(format!("shield   {:.1}/{:.1}", s.current, s.max));
+        }
+        if let Some(p) = ai_pilot.get(inspected) {
+            lines.push(format!("steering {:?}", p.steering));
+        }
+        if let Some(c) = camera.get(inspected) {
+            lines.push(format!("zoom     {:.1}", c.zoom_level));
+            lines.push(format!("offset   {}", Self::format_vector(&c.offset)));
+        }
+        match shape.get(inspected) {
+            Some(Shape::Init { obj_path, .. })
+            | Some(Shape::Unit { obj_path })
+            | Some(Shape::Loading { obj_path }) => lines.push(format!("shape    {}", obj_path)),
+            None => (),
+        }
+        lines.push(format!("timescale {:.2}", time_scale.0));
+        // Wide enough for most values, so the panel does not jump around while they change
+        let x = overlay.width - bitmap_font::ADVANCE as f32 * SCALE * 32.0;
+        Self::panel(&mut overlay, &lines, x, inspector_y, INSPECTOR_COLOR);
+    }
+}
@@ -1,56 +1,342 @@
-use crate::ecs::components::damage::Damage;
+use crate::ecs::components::armor::Armor;
+use crate::ecs::components::damage::{Damage, DamageType};
 use crate::ecs::components::health::Health;
+use crate::ecs::components::invulnerable::Invulnerable;
+use crate::ecs::components::shield::Shield;
 use crate::ecs::resources::collisions::Collisions;
+use crate::ecs::resources::destructions::Destructions;
+use crate::ecs::resources::time_scale::TimeScale;
 use specs::prelude::*;
 
+// Applies the hits of the collisions, what runs out of Health is queued in the Destructions
 pub struct DamageSystem;
 
+impl DamageSystem {
+    // What a hit takes off the Health after the resistances, the shield and the armor, in
+    // that order
+    pub fn hull_damage(
+        damage: f32,
+        damage_type: DamageType,
+        shield: Option<&mut Shield>,
+        armor: Option<&Armor>,
+    ) -> f32 {
+        let mut damage = damage;
+        if let Some(armor) = armor {
+            damage *= 1.0 - armor.resistances.get(damage_type).max(0.0).min(1.0);
+        }
+        if let Some(shield) = shield {
+            let absorbed = damage.min(shield.current).max(0.0);
+            shield.current -= absorbed;
+            shield.since_hit = 0.0;
+            damage -= absorbed;
+        }
+        if let Some(armor) = armor {
+            if damage > 0.0 {
+                damage = (damage - armor.reduction).max(0.0);
+            }
+        }
+        damage
+    }
+}
+
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
         Entities<'a>,
+        Read<'a, TimeScale>,
         Write<'a, Collisions>,
+        Write<'a, Destructions>,
         ReadStorage<'a, Damage>,
         WriteStorage<'a, Health>,
+        WriteStorage<'a, Shield>,
+        ReadStorage<'a, Armor>,
+        WriteStorage<'a, Invulnerable>,
     );
 
-    fn run(&mut self, (entities, collisions, damage, mut health): Self::SystemData) {
-        let mut to_kill: Vec<Entity> = Vec::new();
+    fn run(
+        &mut self,
+        (
+            entities,
+            time_scale,
+            collisions,
+            mut destructions,
+            damage,
+            mut health,
+            mut shield,
+            armor,
+            mut invulnerable,
+        ): Self::SystemData,
+    ) {
+        for s in (&mut shield).join() {
+            s.since_hit += time_scale.0;
+            if s.since_hit >= s.delay {
+                s.current = (s.current + s.regeneration * time_scale.0).min(s.max);
+            }
+        }
+        let mut vulnerable_again: Vec<Entity> = Vec::new();
+        for (e, i) in (&*entities, &mut invulnerable).join() {
+            i.ticks -= time_scale.0;
+            if i.ticks <= 0.0 {
+                vulnerable_again.push(e);
+            }
+        }
+        for e in vulnerable_again {
+            invulnerable.remove(e);
+        }
+
         for (ent1, ent2) in collisions.0.iter() {
-            if let Some(damage_1) = damage.get(*ent1) {
-                if damage_1.damage_dealer != *ent1 {
-                    // We are now "damaging"
-                    if let Some(health_2) = health.get_mut(*ent2) {
-                        health_2.health -= damage_1.damage;
-                        if health_2.health <= 0.0 {
-                            to_kill.push(*ent2);
+            for (attacker, victim) in [(*ent1, *ent2), (*ent2, *ent1)].iter() {
+                let hit = match damage.get(*attacker) {
+                    // Nothing damages the one who dealt it
+                    Some(hit) if hit.damage_dealer != *victim => hit,
+                    _ => continue,
+                };
+                // We are now "damaging"
+                if !invulnerable.contains(*victim) && !destructions.is_destroyed(*victim) {
+                    if let Some(victim_health) = health.get_mut(*victim) {
+                        let armor = armor.get(*victim);
+                        let hull_damage = Self::hull_damage(
+                            hit.damage,
+                            hit.damage_type,
+                            shield.get_mut(*victim),
+                            armor,
+                        );
+                        victim_health.health -= hull_damage;
+                        if victim_health.health <= 0.0 {
+                            destructions.destroy(*victim, Some(hit.damage_dealer));
+                        } else if let Some(armor) = armor {
+                            if hull_damage > 0.0 && armor.invulnerability > 0.0 {
+                                invulnerable.insert(
+                                    *victim,
+                                    Invulnerable {
+                                        ticks: armor.invulnerability,
+                                    },
+                                );
+                            }
                         }
                     }
-                    if damage_1.despawn_entity_on_impact {
-                        to_kill.push(*ent1);
-                    }
                 }
-            }
-            if let Some(damage_2) = damage.get(*ent2) {
-                if damage_2.damage_dealer != *ent2 {
-                    if let Some(health_1) = health.get_mut(*ent1) {
-                        health_1.health -= damage_2.damage;
-                        if health_1.health <= 0.0 {
-                            to_kill.push(*ent1);
-                        }
-                    }
-                    if damage_2.despawn_entity_on_impact {
-                        to_kill.push(*ent2);
-                    }
+                if hit.despawn_entity_on_impact {
+                    destructions.despawn(*attacker);
                 }
             }
         }
-        for e in &to_kill {
-            if let Err(..) = entities.delete(*e) {
-                println!(
-                    "Tried to delete and entity(id: {}) in DamageSystem, but this sadly failed",
-                    e.id()
-                );
-            }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::AABB;
+    use crate::ecs::components::armor::Resistances;
+    use crate::ecs::components::collider::Collider;
+    use crate::ecs::components::shape::Shape;
+    use crate::ecs::components::transform::Transform;
+    use crate::ecs::systems::collision_detector_3::CollisionDetector3;
+    use crate::ecs::world::DoemWorld;
+    use crate::tess_manager::TessHandle;
+    use doem_math::{Matrix4, Vector3};
+
+    fn armor(kinetic: f32, reduction: f32, invulnerability: f32) -> Armor {
+        Armor {
+            resistances: Resistances {
+                kinetic,
+                ..Resistances::default()
+            },
+            reduction,
+            invulnerability,
         }
     }
+
+    fn shield(current: f32) -> Shield {
+        Shield {
+            current,
+            since_hit: 100.0,
+            ..Shield::new(50.0, 0.0, 60.0)
+        }
+    }
+
+    #[test]
+    fn resistances_are_clamped() {
+        let hull = |resistance: f32| {
+            DamageSystem::hull_damage(
+                40.0,
+                DamageType::Kinetic,
+                None,
+                Some(&armor(resistance, 0.0, 0.0)),
+            )
+        };
+        assert_eq!(hull(0.25), 30.0);
+        assert_eq!(hull(1.5), 0.0);
+        assert_eq!(hull(-0.5), 40.0);
+        // Only the resistance of the damage type counts
+        let energy =
+            DamageSystem::hull_damage(40.0, DamageType::Energy, None, Some(&armor(1.0, 0.0, 0.0)));
+        assert_eq!(energy, 40.0);
+    }
+
+    #[test]
+    fn shield_absorbs_before_the_hull() {
+        let mut s = shield(30.0);
+        assert_eq!(
+            DamageSystem::hull_damage(10.0, DamageType::Kinetic, Some(&mut s), None),
+            0.0
+        );
+        assert_eq!(s.current, 20.0);
+        assert_eq!(s.since_hit, 0.0);
+        let mut s = shield(30.0);
+        assert_eq!(
+            DamageSystem::hull_damage(50.0, DamageType::Kinetic, Some(&mut s), None),
+            20.0
+        );
+        assert_eq!(s.current, 0.0);
+        assert_eq!(s.since_hit, 0.0);
+    }
+
+    #[test]
+    fn reduction_only_applies_to_what_gets_through() {
+        let reduce = armor(0.0, 5.0, 0.0);
+        let mut s = shield(30.0);
+        let hull =
+            DamageSystem::hull_damage(50.0, DamageType::Kinetic, Some(&mut s), Some(&reduce));
+        assert_eq!(hull, 15.0);
+        // The reduction does not eat into the shield
+        let mut s = shield(30.0);
+        let hull =
+            DamageSystem::hull_damage(32.0, DamageType::Kinetic, Some(&mut s), Some(&reduce));
+        assert_eq!(hull, 0.0);
+        assert_eq!(s.current, 0.0);
+        let hull = DamageSystem::hull_damage(3.0, DamageType::Kinetic, None, Some(&reduce));
+        assert_eq!(hull, 0.0);
+    }
+
+    fn shape() -> Shape {
+        Shape::Init {
+            obj_path: "box.obj".to_owned(),
+            tess: TessHandle::new(0),
+            bounding_box: AABB {
+                middle_point: Vector3::origin(),
+                half_size: Vector3::from([[1.0], [1.0], [1.0]]),
+            },
+            bounding_box_tess: None,
+        }
+    }
+
+    fn transform(x: f32) -> Transform {
+        Transform {
+            position: Vector3::from([[x], [0.0], [0.0]]),
+            scale: Vector3::from([[1.0], [1.0], [1.0]]),
+            orientation: Matrix4::identity(),
+        }
+    }
+
+    // A ship with the given defences and a bullet of the given damage overlapping it
+    fn hit_world(damage: f32, shield: Option<Shield>, armor: Armor) -> (World, Entity, Entity) {
+        let mut world = DoemWorld::new();
+        System::setup(&mut CollisionDetector3, &mut world);
+        System::setup(&mut DamageSystem, &mut world);
+        let shooter = world.create_entity().build();
+        let mut ship = world
+            .create_entity()
+            .with(shape())
+            .with(transform(0.0))
+            .with(Collider {
+                half_size: Vector3::from([[1.0], [1.0], [1.0]]),
+            })
+            .with(Health { health: 100.0 })
+            .with(armor);
+        if let Some(shield) = shield {
+            ship = ship.with(shield);
+        }
+        let ship = ship.build();
+        let bullet = world
+            .create_entity()
+            .with(shape())
+            .with(transform(1.5))
+            .with(Collider {
+                half_size: Vector3::from([[1.0], [1.0], [1.0]]),
+            })
+            .with(Damage {
+                damage,
+                damage_type: DamageType::Kinetic,
+                despawn_entity_on_impact: false,
+                damage_dealer: shooter,
+            })
+            .build();
+        (world, ship, bullet)
+    }
+
+    fn tick(world: &mut World) {
+        CollisionDetector3.run_now(world);
+        DamageSystem.run_now(world);
+        world.maintain();
+    }
+
+    fn health(world: &World, ship: Entity) -> f32 {
+        world.read_storage::<Health>().get(ship).unwrap().health
+    }
+
+    #[test]
+    fn one_hit_per_contact() {
+        let (mut world, ship, _) = hit_world(40.0, Some(shield(30.0)), armor(0.0, 2.0, 0.0));
+        tick(&mut world);
+        // 30 absorbed by the shield, the armor takes 2 off the other 10, once
+        assert_eq!(health(&world, ship), 92.0);
+        assert_eq!(
+            world.read_storage::<Shield>().get(ship).unwrap().current,
+            0.0
+        );
+        assert_eq!(world.read_resource::<Collisions>().0.len(), 1);
+    }
+
+    #[test]
+    fn absorbed_hits_grant_no_invulnerability() {
+        let (mut world, ship, _) = hit_world(20.0, Some(shield(30.0)), armor(0.0, 0.0, 10.0));
+        tick(&mut world);
+        assert_eq!(health(&world, ship), 100.0);
+        assert_eq!(
+            world.read_storage::<Shield>().get(ship).unwrap().current,
+            10.0
+        );
+        assert!(!world.read_storage::<Invulnerable>().contains(ship));
+    }
+
+    #[test]
+    fn invulnerability_counts_down_and_is_removed() {
+        let (mut world, ship, bullet) = hit_world(20.0, None, armor(0.0, 0.0, 10.0));
+        tick(&mut world);
+        assert_eq!(health(&world, ship), 80.0);
+        let ticks = |world: &World| {
+            world
+                .read_storage::<Invulnerable>()
+                .get(ship)
+                .map(|i| i.ticks)
+        };
+        assert_eq!(ticks(&world), Some(10.0));
+
+        // Still touching the bullet, but it does no harm while the ship is invulnerable
+        for i in 1..10 {
+            tick(&mut world);
+            assert_eq!(ticks(&world), Some(10.0 - i as f32));
+            assert_eq!(health(&world, ship), 80.0);
+        }
+        world.delete_entity(bullet).unwrap();
+        tick(&mut world);
+        assert_eq!(ticks(&world), None);
+        assert_eq!(health(&world, ship), 80.0);
+    }
+
+    #[test]
+    fn time_scale_slows_the_countdown() {
+        let (mut world, ship, bullet) = hit_world(20.0, None, armor(0.0, 0.0, 10.0));
+        tick(&mut world);
+        world.delete_entity(bullet).unwrap();
+        world.insert(TimeScale(0.5));
+        tick(&mut world);
+        let ticks = world
+            .read_storage::<Invulnerable>()
+            .get(ship)
+            .unwrap()
+            .ticks;
+        assert_eq!(ticks, 9.5);
+    }
 }
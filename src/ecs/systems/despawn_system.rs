@@ -1,29 +1,38 @@
 use crate::consts;
 use crate::ecs::components::despawn::Despawn;
+use crate::ecs::components::lifetime::Lifetime;
 use crate::ecs::components::shape::Shape;
 use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::destructions::Destructions;
+use crate::ecs::resources::time_scale::TimeScale;
 use crate::ecs::systems::collision_detector_3::CollisionDetector3;
 use doem_math::{Matrix4, Vector3};
 use specs::prelude::*;
 
+// Removes what left the world or outlived its Lifetime
 pub struct DespawnSystem;
 
 impl<'a> System<'a> for DespawnSystem {
     type SystemData = (
         Entities<'a>,
+        Read<'a, TimeScale>,
+        Write<'a, Destructions>,
         ReadStorage<'a, Transform>,
         ReadStorage<'a, Despawn>,
         ReadStorage<'a, Shape>,
+        WriteStorage<'a, Lifetime>,
     );
 
-    fn run(&mut self, (entities, transform, despawn, shape): Self::SystemData) {
+    fn run(
+        &mut self,
+        (entities, time_scale, mut destructions, transform, despawn, shape, mut lifetime): Self::SystemData,
+    ) {
         let world_bounding_box = consts::WORLD_BOUNDING_BOX.clone();
         let world_transform = Transform {
             position: Vector3::origin(),
             scale: Vector3::from([[1.0], [1.0], [1.0]]),
             orientation: Matrix4::identity(),
         };
-        let mut to_kill: Vec<Entity> = Vec::new();
         for (ent, t, _d, s) in (&*entities, &transform, &despawn, &shape).join() {
             // Sadly you must have a shape right now
             if let Shape::Init { bounding_box, .. } = s {
@@ -33,16 +42,18 @@ impl<'a> System<'a> for DespawnSystem {
                     &bounding_box,
                     &t,
                 ) {
-                    to_kill.push(ent);
+                    destructions.despawn(ent);
                 }
             }
         }
-        for e in &to_kill {
-            if let Err(..) = entities.delete(*e) {
-                println!(
-                    "Tried to delete and entity(id: {}) in DespawnSystem, but this sadly failed",
-                    e.id()
-                );
+        for (ent, t, l) in (&*entities, &transform, &mut lifetime).join() {
+            l.age += time_scale.0;
+            if let Some(last_position) = &l.last_position {
+                l.travelled += (&t.position - last_position).length();
+            }
+            l.last_position = Some(t.position.clone());
+            if l.expired() {
+                destructions.despawn(ent);
             }
         }
     }
@@ -0,0 +1,55 @@
+use crate::ecs::components::particle_emitter::ParticleEmitter;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::debug_draw::{self, DebugDraw};
+use crate::ecs::resources::destructions::Destructions;
+use crate::ecs::resources::particles::Particles;
+use specs::prelude::*;
+
+const DEATH_MARKER_RADIUS: f32 = 20.0;
+
+// The only system which deletes entities, it blows up what was destroyed and deletes
+// everything in the Destructions
+pub struct DestructionSystem;
+
+impl<'a> System<'a> for DestructionSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Destructions>,
+        Write<'a, Particles>,
+        Write<'a, DebugDraw>,
+        ReadStorage<'a, Transform>,
+    );
+
+    fn run(
+        &mut self,
+        (entities, mut destructions, mut particles, mut debug_draw, transform): Self::SystemData,
+    ) {
+        for d in &destructions.destroyed {
+            if let Some(t) = transform.get(d.entity) {
+                particles.burst(&ParticleEmitter::explosion(), &t.position, &t.orientation);
+                debug_draw.sphere(&t.position, DEATH_MARKER_RADIUS, debug_draw::RED);
+            }
+            debug!(
+                "{} was destroyed by {}",
+                d.entity.id(),
+                d.killer
+                    .map_or("nothing".to_owned(), |killer| killer.id().to_string())
+            );
+        }
+        let destroyed = destructions.destroyed.drain(..).map(|d| d.entity);
+        let mut to_delete: Vec<Entity> = destroyed.collect();
+        for e in destructions.despawned.drain(..) {
+            if !to_delete.contains(&e) {
+                to_delete.push(e);
+            }
+        }
+        for e in to_delete {
+            if let Err(..) = entities.delete(e) {
+                warn!(
+                    "Tried to delete and entity(id: {}) in DestructionSystem, but this sadly failed",
+                    e.id()
+                );
+            }
+        }
+    }
+}
@@ -1,57 +1,743 @@
+use crate::asset_error::AssetError;
+use crate::consts;
+use crate::data::AABB;
 use crate::ecs::components::camera::Camera;
+use crate::ecs::components::level_of_detail::LevelOfDetail;
 use crate::ecs::components::shape::Shape;
 use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::asset_stats::AssetStats;
+use crate::ecs::resources::background::{Background, Skybox};
+use crate::ecs::resources::console::Console;
+use crate::ecs::resources::debug_draw::DebugDraw;
 use crate::ecs::resources::doem_events::DoemEvents;
-use crate::gl_common::{ShaderInterface, VertexSemantics};
-use crate::tess_manager::TessManager;
-use doem_math::{Matrix4, Vector3, PI};
+use crate::ecs::resources::frame_check::FrameCheck;
+use crate::ecs::resources::overlay::Overlay;
+use crate::ecs::resources::particles::Particles;
+use crate::ecs::resources::preload::Preload;
+use crate::ecs::resources::profiler::Profiler;
+use crate::ecs::resources::render_stats::RenderStats;
+use crate::file_watcher::FileWatcher;
+use crate::frustum::Frustum;
+use crate::gl_common::{
+    CopyInterface, Instance, OverlayColor, OverlayCorner, OverlayInstance, OverlayInterface,
+    OverlayRect, OverlaySemantics, OverlayVertex, ParticleCorner, ParticleInstance,
+    ParticleInterface, ParticleSemantics, ParticleVertex, ShaderInterface, SkyboxInterface,
+    StarfieldInterface, Vertex, VertexColor, VertexPosition, VertexSemantics,
+};
+use crate::screenshot::{self, Frames};
+use crate::settings::Settings;
+use crate::skybox::CubemapFaces;
+use crate::tess_manager::{TessHandle, TessManager};
+use doem_math::{Matrix4, Vector3};
+use luminance::blending::{Equation, Factor};
 use luminance::context::GraphicsContext;
-use luminance::framebuffer::Framebuffer;
+use luminance::framebuffer::{ColorSlot, DepthSlot, Framebuffer};
 use luminance::pipeline::PipelineState;
+use luminance::pixel::{Depth32F, NormRGB8UI, NormRGBA8UI};
 use luminance::render_state::RenderState;
-use luminance::shader::program::Program;
-use luminance::tess::TessSlice;
-use luminance::texture::{Dim2, Flat};
+use luminance::shader::program::{Program, UniformInterface};
+use luminance::tess::{Mode, Tess, TessBuilder, TessSlice};
+use luminance::texture::{CubeFace, Cubemap, Dim2, Flat, GenMipmaps, MinFilter, Sampler, Texture};
+use luminance::vertex::Semantics;
 use luminance_glfw::{Action, GlfwSurface, Key, Surface, WindowEvent};
 use specs::prelude::*;
 use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const VS: &str = include_str!("../../shaders/displacement-vs.glsl");
 const FS: &str = include_str!("../../shaders/displacement-fs.glsl");
+// Only read at runtime when hot reloading, a program's stages are <name>-vs.glsl and
+// <name>-fs.glsl
+const SHADERS_DIR: &str = "src/shaders";
+const COPY_VS: &str = include_str!("../../shaders/copy-vs.glsl");
+const COPY_FS: &str = include_str!("../../shaders/copy-fs.glsl");
+const SKYBOX_VS: &str = include_str!("../../shaders/skybox-vs.glsl");
+const SKYBOX_FS: &str = include_str!("../../shaders/skybox-fs.glsl");
+const STARFIELD_VS: &str = include_str!("../../shaders/starfield-vs.glsl");
+const STARFIELD_FS: &str = include_str!("../../shaders/starfield-fs.glsl");
+const PARTICLE_VS: &str = include_str!("../../shaders/particle-vs.glsl");
+const PARTICLE_FS: &str = include_str!("../../shaders/particle-fs.glsl");
 
-const FOVY: f32 = PI / 2.0;
-const Z_NEAR: f32 = 0.1;
-const Z_FAR: f32 = 100_000.0;
+const BILLBOARD: [ParticleVertex; 4] = [
+    ParticleVertex {
+        corner: ParticleCorner::new([-1.0, -1.0]),
+    },
+    ParticleVertex {
+        corner: ParticleCorner::new([1.0, -1.0]),
+    },
+    ParticleVertex {
+        corner: ParticleCorner::new([1.0, 1.0]),
+    },
+    ParticleVertex {
+        corner: ParticleCorner::new([-1.0, 1.0]),
+    },
+];
+const INITIAL_PARTICLE_CAPACITY: usize = 1024;
+const OVERLAY_VS: &str = include_str!("../../shaders/overlay-vs.glsl");
+const OVERLAY_FS: &str = include_str!("../../shaders/overlay-fs.glsl");
+
+const OVERLAY_QUAD: [OverlayVertex; 4] = [
+    OverlayVertex {
+        corner: OverlayCorner::new([-1.0, -1.0]),
+    },
+    OverlayVertex {
+        corner: OverlayCorner::new([1.0, -1.0]),
+    },
+    OverlayVertex {
+        corner: OverlayCorner::new([1.0, 1.0]),
+    },
+    OverlayVertex {
+        corner: OverlayCorner::new([-1.0, 1.0]),
+    },
+];
+const INITIAL_OVERLAY_CAPACITY: usize = 1024;
+
+// In the order of the faces of CubemapFaces
+const CUBE_FACES: [CubeFace; 6] = [
+    CubeFace::PositiveX,
+    CubeFace::NegativeX,
+    CubeFace::PositiveY,
+    CubeFace::NegativeY,
+    CubeFace::PositiveZ,
+    CubeFace::NegativeZ,
+];
+
+const SCREENSHOTS_DIR: &str = "screenshots";
+// Where the scene is looked at from when there never was a Camera
+const FALLBACK_EYE: [[f32; 1]; 3] = [[0.0], [500.0], [1500.0]];
+
+// The scene is rendered into this framebuffer, so it can be read back for screenshots
+type Offscreen = Framebuffer<Flat, Dim2, NormRGBA8UI, Depth32F>;
+type SkyboxTexture = Texture<Flat, Cubemap, NormRGB8UI>;
 
 pub struct GLSystem {
     surface: Rc<RefCell<GlfwSurface>>,
     back_buffer: Framebuffer<Flat, Dim2, (), ()>,
+    offscreen: Offscreen,
     tess_manager: TessManager,
     shader_program: Program<VertexSemantics, (), ShaderInterface>,
+    copy_program: Program<(), (), CopyInterface>,
+    skybox_program: Program<(), (), SkyboxInterface>,
+    starfield_program: Program<(), (), StarfieldInterface>,
+    particle_program: Program<ParticleSemantics, (), ParticleInterface>,
+    quad: Tess,
+    // The billboards with an instance buffer for particle_capacity particles
+    particles: Tess,
+    particle_capacity: usize,
+    // How many of the instances are drawn this frame
+    particle_count: usize,
+    // The lines pushed into the DebugDraw resource this frame
+    debug_lines: Option<Tess>,
+    overlay_program: Program<OverlaySemantics, (), OverlayInterface>,
+    // The rectangles of the Overlay resource, like the particles
+    overlay: Tess,
+    overlay_capacity: usize,
+    overlay_count: usize,
+    // The background the skybox and stars were last built for
+    background: Background,
+    skybox: Option<SkyboxTexture>,
+    stars: Option<Tess>,
     should_quit: Arc<Mutex<bool>>,
     draw_bounding_boxes: bool,
+    file_watcher: Option<FileWatcher>,
+    // Recordings and replays block on mesh loads, so the world does not depend on their timing
+    wait_for_loads: bool,
+    take_screenshot: bool,
+    frames: Option<Frames>,
+    frame: u64,
+    fovy: f32,
+    z_near: f32,
+    z_far: f32,
+    // Only the back buffer is multisampled, so the scene is drawn into it directly
+    multisampled: bool,
+    // The view and eye of the last frame with a Camera, kept once the player is gone
+    last_view: Option<(Matrix4, Vector3)>,
 }
 
 impl GLSystem {
-    pub fn new(mut surface: GlfwSurface, should_quit: Arc<Mutex<bool>>) -> Self {
+    pub fn new(
+        mut surface: GlfwSurface,
+        settings: &Settings,
+        should_quit: Arc<Mutex<bool>>,
+        hot_reload: bool,
+        wait_for_loads: bool,
+        frames: Option<Frames>,
+    ) -> Self {
         let back_buffer = surface.back_buffer().unwrap();
+        let offscreen = Self::new_offscreen(&mut surface);
         let shader_program =
             Program::<VertexSemantics, (), ShaderInterface>::from_strings(None, VS, None, FS)
                 .expect("Shaders could not be initialized, bye :(")
                 .ignore_warnings();
+        let copy_program =
+            Program::<(), (), CopyInterface>::from_strings(None, COPY_VS, None, COPY_FS)
+                .expect("Shaders could not be initialized, bye :(")
+                .ignore_warnings();
+        let skybox_program =
+            Program::<(), (), SkyboxInterface>::from_strings(None, SKYBOX_VS, None, SKYBOX_FS)
+                .expect("Shaders could not be initialized, bye :(")
+                .ignore_warnings();
+        let starfield_program = Program::<(), (), StarfieldInterface>::from_strings(
+            None,
+            STARFIELD_VS,
+            None,
+            STARFIELD_FS,
+        )
+        .expect("Shaders could not be initialized, bye :(")
+        .ignore_warnings();
+        let particle_program = Program::<ParticleSemantics, (), ParticleInterface>::from_strings(
+            None,
+            PARTICLE_VS,
+            None,
+            PARTICLE_FS,
+        )
+        .expect("Shaders could not be initialized, bye :(")
+        .ignore_warnings();
+        let particles = Self::new_particles(&mut surface, INITIAL_PARTICLE_CAPACITY);
+        let overlay_program = Program::<OverlaySemantics, (), OverlayInterface>::from_strings(
+            None, OVERLAY_VS, None, OVERLAY_FS,
+        )
+        .expect("Shaders could not be initialized, bye :(")
+        .ignore_warnings();
+        let overlay = Self::new_overlay(&mut surface, INITIAL_OVERLAY_CAPACITY);
+        let quad = TessBuilder::new(&mut surface)
+            .set_vertex_nb(4)
+            .set_mode(Mode::TriangleFan)
+            .build()
+            .unwrap();
         let surface = Rc::new(RefCell::new(surface));
         let tess_manager = TessManager::new(surface.clone());
         let draw_bounding_boxes = false;
+        let file_watcher = if hot_reload {
+            Some(FileWatcher::new(&[SHADERS_DIR, consts::MODELS_DIR]))
+        } else {
+            None
+        };
         Self {
             surface,
             back_buffer,
+            offscreen,
             tess_manager,
             shader_program,
+            copy_program,
+            skybox_program,
+            starfield_program,
+            particle_program,
+            quad,
+            particles,
+            particle_capacity: INITIAL_PARTICLE_CAPACITY,
+            particle_count: 0,
+            debug_lines: None,
+            overlay_program,
+            overlay,
+            overlay_capacity: INITIAL_OVERLAY_CAPACITY,
+            overlay_count: 0,
+            background: Background::default(),
+            skybox: None,
+            stars: None,
             should_quit,
             draw_bounding_boxes,
+            file_watcher,
+            wait_for_loads,
+            take_screenshot: false,
+            frames,
+            frame: 0,
+            fovy: settings.fov_radians(),
+            z_near: settings.z_near,
+            z_far: settings.z_far,
+            multisampled: settings.msaa > 0,
+            last_view: None,
+        }
+    }
+    fn fallback_view() -> (Matrix4, Vector3) {
+        let eye = Vector3::from(FALLBACK_EYE);
+        let up = Vector3::from([[0.0], [1.0], [0.0]]);
+        (Matrix4::get_view(&eye, &Vector3::origin(), &up), eye)
+    }
+    fn new_offscreen(surface: &mut GlfwSurface) -> Offscreen {
+        let size = surface.size();
+        Framebuffer::new(surface, size, 0, Sampler::default())
+            .expect("The offscreen framebuffer could not be created")
+    }
+    fn new_particles(surface: &mut GlfwSurface, capacity: usize) -> Tess {
+        let instances = vec![ParticleInstance::from_values([0.0; 3], [0.0; 4], 0.0); capacity];
+        TessBuilder::new(surface)
+            .add_vertices(BILLBOARD)
+            .add_instances(instances)
+            .set_mode(Mode::TriangleFan)
+            .build()
+            .expect("The particle billboards could not be built")
+    }
+    // Sorted from far to near, so nearer particles are blended over the ones behind them
+    fn upload_particles(&mut self, particles: &Particles, eye: &Vector3) {
+        let eye = [eye[0][0], eye[1][0], eye[2][0]];
+        let distance = |position: &[f32; 3]| {
+            (0..3)
+                .map(|i| (position[i] - eye[i]) * (position[i] - eye[i]))
+                .sum::<f32>()
+        };
+        let mut sorted: Vec<(f32, ParticleInstance)> = particles
+            .particles
+            .iter()
+            .map(|p| {
+                (
+                    distance(&p.position),
+                    ParticleInstance::from_values(p.position, p.color(), p.size()),
+                )
+            })
+            .collect();
+        sorted.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        if sorted.len() > self.particle_capacity {
+            self.particle_capacity = sorted.len().next_power_of_two();
+            self.particles =
+                Self::new_particles(&mut *self.surface.borrow_mut(), self.particle_capacity);
+        }
+        self.particle_count = match self.particles.as_inst_slice_mut::<ParticleInstance>() {
+            Ok(mut slice) => {
+                for (i, (_, instance)) in sorted.iter().enumerate() {
+                    slice[i] = *instance;
+                }
+                sorted.len()
+            }
+            Err(e) => {
+                error!("the particles could not be uploaded: {}", e);
+                0
+            }
+        };
+    }
+    fn render_scene<CS, DS>(
+        &self,
+        framebuffer: &Framebuffer<Flat, Dim2, CS, DS>,
+        projection: &Matrix4,
+        view: &Matrix4,
+        eye: &Vector3,
+        batches: &BTreeMap<usize, Vec<Instance>>,
+    ) where
+        CS: ColorSlot<Flat, Dim2>,
+        DS: DepthSlot<Flat, Dim2>,
+    {
+        let shader_program = &self.shader_program;
+        let tess_manager = &self.tess_manager;
+        let background = &self.background;
+        self.surface.borrow_mut().pipeline_builder().pipeline(
+            framebuffer,
+            &PipelineState::default(),
+            |pipeline, mut shd_gate| {
+                // The background is drawn without depth testing, so it never hides the scene
+                let background_state = RenderState::default().set_depth_test(None);
+                if let Some(skybox) = &self.skybox {
+                    let skybox = pipeline.bind_texture(skybox);
+                    shd_gate.shade(&self.skybox_program, |iface, mut rdr_gate| {
+                        iface.projection.update(projection.transpose().into());
+                        iface.view.update(view.transpose().into());
+                        iface.skybox.update(&skybox);
+                        rdr_gate.render(&background_state, |mut tess_gate| {
+                            tess_gate.render(TessSlice::one_whole(&self.quad));
+                        });
+                    });
+                }
+                if let (Some(stars), Some(starfield)) = (&self.stars, &background.starfield) {
+                    // Stars are added onto the skybox
+                    let star_state = background_state.set_blending((
+                        Equation::Additive,
+                        Factor::One,
+                        Factor::One,
+                    ));
+                    shd_gate.shade(&self.starfield_program, |iface, mut rdr_gate| {
+                        iface.projection.update(projection.transpose().into());
+                        iface.view.update(view.transpose().into());
+                        iface.eye.update([eye[0][0], eye[1][0], eye[2][0]]);
+                        iface
+                            .stars_per_layer
+                            .update(starfield.stars_per_layer as i32);
+                        iface.layer_size.update(starfield.layer_size);
+                        iface.brightness.update(starfield.brightness);
+                        rdr_gate.render(&star_state, |mut tess_gate| {
+                            tess_gate.render(TessSlice::one_whole(stars));
+                        });
+                    });
+                }
+                shd_gate.shade(shader_program, |iface, mut rdr_gate| {
+                    iface.projection.update(projection.transpose().into());
+                    iface.view.update(view.transpose().into());
+
+                    rdr_gate.render(&RenderState::default(), |mut tess_gate| {
+                        // Render every tesselation once for all of its instances
+                        for (id, instances) in batches {
+                            let tess_ref = tess_manager
+                                .get_tess(*id)
+                                .expect("tess with tess_id could not be retreived");
+                            tess_gate.render(TessSlice::inst_whole(tess_ref, instances.len()));
+                        }
+                        if let Some(debug_lines) = &self.debug_lines {
+                            tess_gate.render(TessSlice::inst_whole(debug_lines, 1));
+                        }
+                    });
+                });
+                if self.particle_count > 0 {
+                    let particle_state = RenderState::default().set_blending((
+                        Equation::Additive,
+                        Factor::SrcAlpha,
+                        Factor::One,
+                    ));
+                    shd_gate.shade(&self.particle_program, |iface, mut rdr_gate| {
+                        iface.projection.update(projection.transpose().into());
+                        iface.view.update(view.transpose().into());
+                        rdr_gate.render(&particle_state, |mut tess_gate| {
+                            tess_gate.render(TessSlice::inst_whole(
+                                &self.particles,
+                                self.particle_count,
+                            ));
+                        });
+                    });
+                }
+                if self.overlay_count > 0 {
+                    let overlay_state = RenderState::default().set_depth_test(None).set_blending((
+                        Equation::Additive,
+                        Factor::SrcAlpha,
+                        Factor::SrcAlphaComplement,
+                    ));
+                    let (width, height) = (framebuffer.width(), framebuffer.height());
+                    shd_gate.shade(&self.overlay_program, |iface, mut rdr_gate| {
+                        iface.screen_size.update([width as f32, height as f32]);
+                        rdr_gate.render(&overlay_state, |mut tess_gate| {
+                            tess_gate
+                                .render(TessSlice::inst_whole(&self.overlay, self.overlay_count));
+                        });
+                    });
+                }
+            },
+        );
+    }
+    fn new_overlay(surface: &mut GlfwSurface, capacity: usize) -> Tess {
+        let instances =
+            vec![
+                OverlayInstance::new(OverlayRect::new([0.0; 4]), OverlayColor::new([0.0; 4]));
+                capacity
+            ];
+        TessBuilder::new(surface)
+            .add_vertices(OVERLAY_QUAD)
+            .add_instances(instances)
+            .set_mode(Mode::TriangleFan)
+            .build()
+            .expect("The overlay rectangles could not be built")
+    }
+    // Rectangles are drawn in the order they were pushed, later ones end up on top
+    fn upload_overlay(&mut self, overlay: &Overlay) {
+        if overlay.rects.len() > self.overlay_capacity {
+            self.overlay_capacity = overlay.rects.len().next_power_of_two();
+            self.overlay =
+                Self::new_overlay(&mut *self.surface.borrow_mut(), self.overlay_capacity);
+        }
+        self.overlay_count = match self.overlay.as_inst_slice_mut::<OverlayInstance>() {
+            Ok(mut slice) => {
+                for (i, r) in overlay.rects.iter().enumerate() {
+                    slice[i] = OverlayInstance::new(
+                        OverlayRect::new([r.x, r.y, r.width, r.height]),
+                        OverlayColor::new(r.color),
+                    );
+                }
+                overlay.rects.len()
+            }
+            Err(e) => {
+                error!("the overlay could not be uploaded: {}", e);
+                0
+            }
+        };
+    }
+    fn upload_debug_lines(&mut self, debug_draw: &DebugDraw) {
+        if debug_draw.lines.is_empty() {
+            self.debug_lines = None;
+            return;
+        }
+        let vertices: Vec<Vertex> = debug_draw
+            .lines
+            .iter()
+            .flat_map(|line| {
+                let color = VertexColor::new(line.color);
+                vec![
+                    Vertex {
+                        pos: VertexPosition::new(line.from),
+                        color,
+                    },
+                    Vertex {
+                        pos: VertexPosition::new(line.to),
+                        color,
+                    },
+                ]
+            })
+            .collect();
+        // The shader expects a transformation per instance, the lines are in world space already
+        let identity = vec![Instance::from_transform(&Matrix4::identity())];
+        self.debug_lines = match TessBuilder::new(&mut *self.surface.borrow_mut())
+            .add_vertices(vertices)
+            .add_instances(identity)
+            .set_mode(Mode::Line)
+            .build()
+        {
+            Ok(tess) => Some(tess),
+            Err(e) => {
+                error!("the debug lines could not be built: {:?}", e);
+                None
+            }
+        };
+    }
+    // Rebuilds the skybox and stars when the scene changed its background
+    fn apply_background(&mut self, background: &Background) {
+        if *background == self.background {
+            return;
+        }
+        self.skybox = match &background.skybox {
+            Some(skybox) => match self.build_skybox(skybox) {
+                Ok(texture) => Some(texture),
+                Err(e) => {
+                    error!("the skybox could not be built: {}", e);
+                    None
+                }
+            },
+            None => None,
+        };
+        self.stars = match &background.starfield {
+            Some(starfield) if starfield.layers * starfield.stars_per_layer > 0 => Some(
+                TessBuilder::new(&mut *self.surface.borrow_mut())
+                    .set_vertex_nb((starfield.layers * starfield.stars_per_layer) as usize)
+                    .set_mode(Mode::Point)
+                    .build()
+                    .unwrap(),
+            ),
+            _ => None,
+        };
+        self.background = background.clone();
+    }
+    fn build_skybox(&mut self, skybox: &Skybox) -> Result<SkyboxTexture, AssetError> {
+        let cubemap = match skybox {
+            Skybox::Faces { dir } => CubemapFaces::load(dir)?,
+            Skybox::Gradient {
+                zenith,
+                horizon,
+                nadir,
+            } => CubemapFaces::gradient(zenith, horizon, nadir),
+        };
+        let sampler = Sampler {
+            min_filter: MinFilter::Linear,
+            ..Sampler::default()
+        };
+        let texture = Texture::new(&mut *self.surface.borrow_mut(), cubemap.size, 0, sampler)?;
+        for (face, texels) in CUBE_FACES.iter().zip(&cubemap.faces) {
+            texture.upload_part_raw(GenMipmaps::No, ([0, 0], *face), cubemap.size, texels)?;
+        }
+        Ok(texture)
+    }
+    fn copy_offscreen(&self) {
+        let copy_program = &self.copy_program;
+        let offscreen = &self.offscreen;
+        let quad = &self.quad;
+        self.surface.borrow_mut().pipeline_builder().pipeline(
+            &self.back_buffer,
+            &PipelineState::default(),
+            |pipeline, mut shd_gate| {
+                let source = pipeline.bind_texture(offscreen.color_slot());
+                shd_gate.shade(copy_program, |iface, mut rdr_gate| {
+                    iface.source.update(&source);
+                    rdr_gate.render(&RenderState::default(), |mut tess_gate| {
+                        tess_gate.render(TessSlice::one_whole(quad));
+                    });
+                });
+            },
+        );
+    }
+    fn is_capturing(&self) -> bool {
+        self.take_screenshot || self.frames.is_some()
+    }
+    // Writes the requested screenshot and, when rendering or comparing frames, handles the
+    // current frame
+    fn capture(&mut self, frame_check: &mut FrameCheck) {
+        if self.take_screenshot {
+            self.take_screenshot = false;
+            let seconds = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let file_name = format!("doem-{}-{}.png", seconds, self.frame);
+            self.save_frame(&Path::new(SCREENSHOTS_DIR).join(file_name));
+        }
+        if let Some(frames) = &self.frames {
+            let path = frames.path(self.frame);
+            match frames {
+                Frames::Render(_) => self.save_frame(&path),
+                Frames::Compare(_) => {
+                    frame_check.compared += 1;
+                    if !self.compare_frame(&path) {
+                        frame_check.differing.push(self.frame);
+                    }
+                }
+            }
+        }
+        self.frame += 1;
+    }
+    fn compare_frame(&self, path: &Path) -> bool {
+        let (width, height) = (self.offscreen.width(), self.offscreen.height());
+        let (golden_width, golden_height, golden) = match screenshot::load_png(path) {
+            Ok(golden) => golden,
+            Err(e) => {
+                error!("Could not read the golden frame {}: {}", path.display(), e);
+                return false;
+            }
+        };
+        if (golden_width, golden_height) != (width, height) {
+            error!(
+                "{} is {}x{}, the frame is {}x{}",
+                path.display(),
+                golden_width,
+                golden_height,
+                width,
+                height
+            );
+            return false;
+        }
+        let texels = self.offscreen.color_slot().get_raw_texels();
+        let rendered = screenshot::flip_rows(width, &texels);
+        let differing =
+            screenshot::differing_pixels(&rendered, &golden, screenshot::FRAME_TOLERANCE);
+        if differing > 0 {
+            error!("{} pixels differ from {}", differing, path.display());
+        }
+        differing == 0
+    }
+    fn save_frame(&self, path: &Path) {
+        if let Some(dir) = path.parent() {
+            if let Err(e) = fs::create_dir_all(dir) {
+                error!("Could not create {}: {}", dir.display(), e);
+                return;
+            }
+        }
+        let texels = self.offscreen.color_slot().get_raw_texels();
+        let (width, height) = (self.offscreen.width(), self.offscreen.height());
+        if let Err(e) = screenshot::save_png(path, width, height, &texels) {
+            error!("Could not save {}: {}", path.display(), e);
+        }
+    }
+    fn hot_reload(&mut self) {
+        let changed = match &mut self.file_watcher {
+            Some(file_watcher) => file_watcher.poll(),
+            None => return,
+        };
+        let mut programs: BTreeSet<String> = BTreeSet::new();
+        for path in changed {
+            if path.starts_with(SHADERS_DIR) {
+                let name = path.file_name().and_then(|n| n.to_str()).and_then(|n| {
+                    n.trim_end_matches(".glsl")
+                        .rsplitn(2, '-')
+                        .nth(1)
+                        .map(str::to_owned)
+                });
+                programs.extend(name);
+            } else if path.extension().map_or(false, |e| e == "obj") {
+                self.tess_manager.reload(&path.to_string_lossy());
+            }
+        }
+        for name in programs {
+            self.reload_shaders(&name);
+        }
+    }
+    // The debug lines are drawn with the displacement program
+    fn reload_shaders(&mut self, name: &str) {
+        match name {
+            "displacement" => {
+                if let Some(program) = Self::load_program(name) {
+                    self.shader_program = program;
+                }
+            }
+            "copy" => {
+                if let Some(program) = Self::load_program(name) {
+                    self.copy_program = program;
+                }
+            }
+            "skybox" => {
+                if let Some(program) = Self::load_program(name) {
+                    self.skybox_program = program;
+                }
+            }
+            "starfield" => {
+                if let Some(program) = Self::load_program(name) {
+                    self.starfield_program = program;
+                }
+            }
+            "particle" => {
+                if let Some(program) = Self::load_program(name) {
+                    self.particle_program = program;
+                }
+            }
+            "overlay" => {
+                if let Some(program) = Self::load_program(name) {
+                    self.overlay_program = program;
+                }
+            }
+            _ => warn!("No program uses the {} shaders", name),
+        }
+    }
+    // A broken shader is reported and None is returned, so the old program is kept in use
+    fn load_program<S, I>(name: &str) -> Option<Program<S, (), I>>
+    where
+        S: Semantics,
+        I: UniformInterface,
+    {
+        let read = |stage: &str| {
+            fs::read_to_string(Path::new(SHADERS_DIR).join(format!("{}-{}.glsl", name, stage)))
+        };
+        let sources = read("vs").and_then(|vs_source| Ok((vs_source, read("fs")?)));
+        let (vs_source, fs_source) = match sources {
+            Ok(sources) => sources,
+            Err(e) => {
+                error!(
+                    "Could not read the {} shaders, keeping the old ones: {}",
+                    name, e
+                );
+                return None;
+            }
+        };
+        match Program::<S, (), I>::from_strings(None, &vs_source, None, &fs_source) {
+            Ok(program) => {
+                info!("Reloaded the {} shaders", name);
+                Some(program.ignore_warnings())
+            }
+            Err(e) => {
+                error!(
+                    "Could not compile the {} shaders, keeping the old ones: {}",
+                    name, e
+                );
+                None
+            }
+        }
+    }
+    // Initializes the shape when needed, it keeps its tesselation alive through its handle
+    fn update_shape(&mut self, s: &mut Shape, reloaded: &[String]) {
+        if let Shape::Init { obj_path, .. } = s {
+            if reloaded.contains(obj_path) {
+                *s = Shape::Unit {
+                    obj_path: obj_path.clone(),
+                };
+            }
+        }
+        match s {
+            Shape::Init { .. } => (),
+            _ => *s = self.tess_manager.init_shape((*s).clone()),
+        }
+    }
+    fn get_aabb_tess(tess_manager: &mut TessManager, aabb: &AABB) -> Option<TessHandle> {
+        match tess_manager.get_aabb_tess(aabb) {
+            Ok(tess) => Some(tess),
+            Err(e) => {
+                error!("the bounding box could not be built: {}", e);
+                None
+            }
         }
     }
 }
@@ -59,19 +745,60 @@ impl GLSystem {
 impl<'a> System<'a> for GLSystem {
     type SystemData = (
         Write<'a, DoemEvents>,
+        Read<'a, Background>,
+        Read<'a, Particles>,
+        Write<'a, Overlay>,
+        Write<'a, DebugDraw>,
+        Write<'a, Console>,
+        Write<'a, Preload>,
+        Write<'a, AssetStats>,
+        Write<'a, RenderStats>,
+        Write<'a, FrameCheck>,
+        Write<'a, Profiler>,
         WriteStorage<'a, Transform>,
         WriteStorage<'a, Shape>,
+        WriteStorage<'a, LevelOfDetail>,
         ReadStorage<'a, Camera>,
     );
 
-    fn run(&mut self, (mut events, transform, mut shape, camera): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            mut events,
+            background,
+            particles,
+            mut overlay,
+            mut debug_draw,
+            mut console,
+            mut preload,
+            mut asset_stats,
+            mut render_stats,
+            mut frame_check,
+            mut profiler,
+            transform,
+            mut shape,
+            mut level_of_detail,
+            camera,
+        ): Self::SystemData,
+    ) {
+        // The systems are timed as a whole, these are the parts of the GLSystem worth a look
+        let recorder = profiler.recorder();
+        let reloaded = {
+            let _scope = recorder.scope("tess_manager");
+            self.tess_manager.preload(&preload.0);
+            preload.0.clear();
+            self.hot_reload();
+            self.tess_manager.poll_loaded(self.wait_for_loads)
+        };
+        self.apply_background(&background);
+
         let projection = Matrix4::get_projection(
-            FOVY,
+            self.fovy,
             self.surface.borrow().width() as f32 / self.surface.borrow().height() as f32,
-            Z_NEAR,
-            Z_FAR,
+            self.z_near,
+            self.z_far,
         );
-        let mut view: Option<Matrix4> = None;
+        let mut view: Option<(Matrix4, Vector3)> = None;
         for (t, c) in (&transform, &camera).join() {
             let camera_at_origin = &c.offset * c.zoom_level;
             let camera_at_origin_rotated =
@@ -79,107 +806,168 @@ impl<'a> System<'a> for GLSystem {
             let eye = &t.position + &camera_at_origin_rotated.dimension_hop();
             let look_at = &t.position;
             let up = &t.orientation * &Vector3::from([[0.0], [1.0], [0.0]]).dimension_hop();
-            view = Some(Matrix4::get_view(&eye, look_at, &up.dimension_hop()));
+            let view_matrix = Matrix4::get_view(&eye, look_at, &up.dimension_hop());
+            view = Some((view_matrix, eye));
         }
-        let view = view.expect("No View was found!");
+        let (view, eye) = match view {
+            Some(view) => {
+                self.last_view = Some(view.clone());
+                view
+            }
+            // After a game over the scene stays in view from where the camera was
+            None => self.last_view.clone().unwrap_or_else(Self::fallback_view),
+        };
 
-        for s in (&mut shape).join() {
-            if let Shape::Unit { .. } = s {
-                *s = self.tess_manager.init_shape((*s).clone());
+        for lod in (&mut level_of_detail).join() {
+            for level in lod.levels.iter_mut() {
+                self.update_shape(&mut level.shape, &reloaded);
             }
+        }
+        for s in (&mut shape).join() {
+            self.update_shape(s, &reloaded);
 
             if let Shape::Init {
                 bounding_box,
-                bounding_box_tess_id,
+                bounding_box_tess,
                 ..
             } = s
             {
                 if self.draw_bounding_boxes {
-                    match bounding_box_tess_id {
-                        None => {
-                            let id = self.tess_manager.get_aabb_id(&bounding_box);
-                            *bounding_box_tess_id = Some(id);
-                        }
-                        Some(id) => {
-                            let tess = self.tess_manager.get_tess(*id);
-                            if tess.is_none() {
-                                let id = self.tess_manager.get_aabb_id(&bounding_box);
-                                *bounding_box_tess_id = Some(id);
-                            }
-                        }
+                    if bounding_box_tess.is_none() {
+                        *bounding_box_tess =
+                            Self::get_aabb_tess(&mut self.tess_manager, &bounding_box);
                     }
+                } else {
+                    // Let go of the bounding box, so the TessManager can release it
+                    *bounding_box_tess = None;
                 }
             }
         }
 
-        let shader_program = &self.shader_program;
-        let view = view;
-        let tess_manager = &mut self.tess_manager;
-        let draw_bounding_boxes = &self.draw_bounding_boxes;
-        self.surface.borrow_mut().pipeline_builder().pipeline(
-            &self.back_buffer,
-            &PipelineState::default(),
-            |_, mut shd_gate| {
-                shd_gate.shade(shader_program, |iface, mut rdr_gate| {
-                    iface
-                        .projection
-                        .update(projection.transpose().into());
-                    iface.view.update(view.transpose().into());
+        let frustum = Frustum::from_matrix(&(&projection * &view));
+        let mut drawn_entities = 0;
+        let mut culled_entities = 0;
 
-                    rdr_gate.render(&RenderState::default(), |mut tess_gate| {
-                        // Render all the tesselations with their transformations
-                        iface
-                            .transform
-                            .update(Matrix4::identity().transpose().into());
-                        for (s, t) in (&shape, &transform).join() {
-                            if let Shape::Init {
-                                tess_id,
-                                bounding_box_tess_id,
-                                ..
-                            } = s
-                            {
-                                let translation = Matrix4::get_translation(&t.position);
-                                let scaling = Matrix4::get_scaling(&t.scale);
-
-                                let transform = &translation * &(&t.orientation * &scaling);
-                                iface
-                                    .transform
-                                    .update(transform.transpose().into());
-
-                                let tess_ref = tess_manager.get_tess(*tess_id).expect("tess with tess_id could not be retreived");
-                                tess_gate.render(TessSlice::one_whole(tess_ref));
-                                if *draw_bounding_boxes {
-                                    if let Some(id) = bounding_box_tess_id {
-                                        let bounding_box_tess_ref = tess_manager.get_tess(*id).unwrap();
-                                        tess_gate.render(TessSlice::one_whole(bounding_box_tess_ref));
-                                    } else {
-                                        println!("ERROR, we are drawing bounding boxes, but a shape doesn't have a bounding_box_tess_id");
-                                    }
-                                }
-                            }
-                        }
-                    });
-                });
-            },
-        );
+        // Group the instances by tesselation, so every mesh is drawn with a single call
+        let mut batches: BTreeMap<usize, Vec<Instance>> = BTreeMap::new();
+        for (s, t, lod) in (&shape, &transform, (&mut level_of_detail).maybe()).join() {
+            if let Shape::Init {
+                tess,
+                bounding_box,
+                bounding_box_tess,
+                ..
+            } = s
+            {
+                let translation = Matrix4::get_translation(&t.position);
+                let scaling = Matrix4::get_scaling(&t.scale);
+                let model = &translation * &(&t.orientation * &scaling);
+                if !frustum.intersects(&bounding_box.transformed(&model)) {
+                    culled_entities += 1;
+                    continue;
+                }
+                drawn_entities += 1;
+                let instance = Instance::from_transform(&model);
 
-        self.surface.borrow_mut().swap_buffers();
+                let mut mesh_id = tess.id();
+                if let Some(lod) = lod {
+                    let level = lod.select((&t.position - &eye).length());
+                    let tess_manager = &self.tess_manager;
+                    if let Some(id) = lod.loaded_mesh(level, |id| tess_manager.is_placeholder(id)) {
+                        mesh_id = id;
+                    }
+                }
+                batches
+                    .entry(mesh_id)
+                    .or_insert_with(Vec::new)
+                    .push(instance);
+                if let Some(bounding_box_tess) = bounding_box_tess {
+                    batches
+                        .entry(bounding_box_tess.id())
+                        .or_insert_with(Vec::new)
+                        .push(instance);
+                }
+            }
+        }
+        let mut failed: Vec<usize> = Vec::new();
+        for (id, instances) in &batches {
+            if let Err(e) = self.tess_manager.set_instances(*id, instances) {
+                error!(
+                    "the instances of tesselation {} could not be uploaded: {}",
+                    id, e
+                );
+                failed.push(*id);
+            }
+        }
+        for id in failed {
+            batches.remove(&id);
+        }
+        self.upload_particles(&particles, &eye);
+        self.upload_debug_lines(&debug_draw);
+        self.upload_overlay(&overlay);
+        overlay.clear();
+        let [width, height] = self.surface.borrow().size();
+        overlay.width = width as f32;
+        overlay.height = height as f32;
+        debug_draw.clear();
+        *render_stats = RenderStats {
+            drawn_entities,
+            culled_entities,
+        };
+
+        if self.multisampled {
+            self.render_scene(&self.back_buffer, &projection, &view, &eye, &batches);
+            // Screenshots and recorded frames still need the scene in the offscreen framebuffer
+            if self.is_capturing() {
+                self.render_scene(&self.offscreen, &projection, &view, &eye, &batches);
+            }
+        } else {
+            self.render_scene(&self.offscreen, &projection, &view, &eye, &batches);
+            self.copy_offscreen();
+        }
+        self.capture(&mut frame_check);
+
+        {
+            let _scope = recorder.scope("swap_buffers");
+            self.surface.borrow_mut().swap_buffers();
+        }
+
+        {
+            let _scope = recorder.scope("tess_manager");
+            self.tess_manager.release_unused();
+        }
+        *asset_stats = self.tess_manager.stats();
 
         let mut resize = false;
 
         events.0.clear();
         for event in self.surface.borrow_mut().poll_events() {
             match event {
-                WindowEvent::Close | WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+                WindowEvent::Close => {
                     *(*self.should_quit).lock().unwrap() = true;
                 }
                 WindowEvent::FramebufferSize(..) => {
                     resize = true;
                 }
+                WindowEvent::Key(Key::GraveAccent, _, Action::Press, _) => {
+                    console.toggle();
+                }
+                e @ WindowEvent::Key(..) | e @ WindowEvent::Char(..) if console.open => {
+                    console.handle(e);
+                }
+                WindowEvent::Key(Key::Escape, _, Action::Release, _) => {
+                    *(*self.should_quit).lock().unwrap() = true;
+                }
                 WindowEvent::Key(Key::B, _, action, _)
                     if action == Action::Press || action == Action::Repeat =>
                 {
                     self.draw_bounding_boxes = !self.draw_bounding_boxes;
+                    debug_draw.enabled = self.draw_bounding_boxes;
+                }
+                WindowEvent::Key(Key::P, _, Action::Press, _) => {
+                    self.take_screenshot = true;
+                }
+                WindowEvent::Key(Key::F3, _, Action::Press, _) => {
+                    profiler.visible = !profiler.visible;
                 }
                 e => {
                     (events.0).push(e);
@@ -189,10 +977,20 @@ impl<'a> System<'a> for GLSystem {
 
         if resize {
             self.back_buffer = self.surface.borrow_mut().back_buffer().unwrap();
+            self.offscreen = Self::new_offscreen(&mut *self.surface.borrow_mut());
         }
     }
     fn setup(&mut self, world: &mut World) {
         Self::SystemData::setup(world);
         world.write_resource::<DoemEvents>();
+        world.write_resource::<Background>();
+        world.write_resource::<Particles>();
+        world.write_resource::<DebugDraw>();
+        world.write_resource::<Console>();
+        world.write_resource::<Overlay>();
+        world.write_resource::<Preload>();
+        world.write_resource::<AssetStats>();
+        world.write_resource::<RenderStats>();
+        world.write_resource::<Profiler>();
     }
 }
@@ -0,0 +1,39 @@
+use crate::ecs::components::homing::Homing;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::time_scale::TimeScale;
+use crate::ecs::systems::ai_system::AiSystem;
+use doem_math::Vector3;
+use specs::prelude::*;
+
+// Steers homing projectiles, they fly straight on once their target is gone
+pub struct HomingSystem;
+
+impl<'a> System<'a> for HomingSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, Homing>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (entities, time_scale, homing, mut transform): Self::SystemData) {
+        let mut turns: Vec<(Entity, Vector3, f32)> = Vec::new();
+        for (e, h, t) in (&*entities, &homing, &transform).join() {
+            if !entities.is_alive(h.target) {
+                continue;
+            }
+            if let Some(target_t) = transform.get(h.target) {
+                let to_target = &target_t.position - &t.position;
+                if to_target.length() > 0.0 {
+                    turns.push((e, to_target.normalize(), h.turn_rate));
+                }
+            }
+        }
+        for (e, direction, turn_rate) in turns {
+            if let Some(t) = transform.get_mut(e) {
+                t.orientation =
+                    AiSystem::turn(&t.orientation, &direction, turn_rate * time_scale.0);
+            }
+        }
+    }
+}
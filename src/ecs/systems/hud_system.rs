@@ -0,0 +1,192 @@
+use crate::ecs::components::camera::Camera;
+use crate::ecs::components::gun::Gun;
+use crate::ecs::components::health::Health;
+use crate::ecs::components::lives::Lives;
+use crate::ecs::components::physics::Physics;
+use crate::ecs::components::shield::Shield;
+use crate::ecs::components::thruster::Thruster;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::game_state::GameState;
+use crate::ecs::resources::hud::{Hud, HudLayout, HudPosition, HudTarget, HudWeapon};
+use crate::ecs::resources::overlay::Overlay;
+use crate::ecs::resources::score::Score;
+use specs::prelude::*;
+
+// Fills the Hud resource for the entity the camera follows and lays it out in the Overlay
+pub struct HudSystem;
+
+impl HudSystem {
+    fn text(
+        overlay: &mut Overlay,
+        layout: &HudLayout,
+        position: &HudPosition,
+        text: &str,
+        color: [f32; 4],
+    ) {
+        let size = Overlay::text_size(text, layout.scale);
+        let (x, y) = position.place(size, (overlay.width, overlay.height));
+        overlay.text(text, x, y, layout.scale, color);
+    }
+    // Four bars around a gap in the middle
+    fn crosshair(overlay: &mut Overlay, layout: &HudLayout) {
+        let size = layout.crosshair_size;
+        let thickness = layout.scale;
+        let (left, top) = layout
+            .crosshair
+            .place((size * 2.0, size * 2.0), (overlay.width, overlay.height));
+        let (x, y) = (left + size, top + size);
+        let gap = size / 3.0;
+        let length = size - gap;
+        let color = layout.color;
+        overlay.rect(x - size, y - thickness / 2.0, length, thickness, color);
+        overlay.rect(x + gap, y - thickness / 2.0, length, thickness, color);
+        overlay.rect(x - thickness / 2.0, y - size, thickness, length, color);
+        overlay.rect(x - thickness / 2.0, y + gap, thickness, length, color);
+    }
+}
+
+impl<'a> System<'a> for HudSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Hud>,
+        Read<'a, HudLayout>,
+        Read<'a, Score>,
+        Read<'a, GameState>,
+        Write<'a, Overlay>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Physics>,
+        ReadStorage<'a, Thruster>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Gun>,
+        ReadStorage<'a, Shield>,
+        ReadStorage<'a, Lives>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            entities,
+            mut hud,
+            layout,
+            score,
+            game_state,
+            mut overlay,
+            camera,
+            transform,
+            physics,
+            thruster,
+            health,
+            gun,
+            shield,
+            lives,
+        ): Self::SystemData,
+    ) {
+        let player = (&*entities, &camera, &transform)
+            .join()
+            .next()
+            .map(|(e, _, t)| (e, t.position.clone()));
+        let (player, position) = match player {
+            Some(player) => player,
+            None => {
+                *hud = Hud {
+                    kills: score.kills,
+                    game_over: game_state.game_over,
+                    ..Hud::default()
+                };
+                if layout.visible && hud.game_over {
+                    Self::text(
+                        &mut overlay,
+                        &layout,
+                        &layout.game_over,
+                        &hud.game_over_text(),
+                        layout.warning_color,
+                    );
+                }
+                return;
+            }
+        };
+
+        // The nearest other entity which can be damaged
+        let target = (&*entities, &transform, &health)
+            .join()
+            .filter(|(e, _, _)| *e != player)
+            .map(|(_, t, h)| HudTarget {
+                health: h.health,
+                distance: (&t.position - &position).length(),
+            })
+            .min_by(|a, b| {
+                a.distance
+                    .partial_cmp(&b.distance)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+        *hud = Hud {
+            speed: physics.get(player).map_or(0.0, |p| p.velocity.length()),
+            thrust: thruster.get(player).map_or(0.0, |t| t.power.length()),
+            health: health.get(player).map(|h| h.health),
+            shield: shield.get(player).map(|s| s.current),
+            lives: lives.get(player).map(|l| l.remaining),
+            target,
+            weapon: gun.get(player).map(|g| HudWeapon {
+                name: g.weapon.name.clone(),
+                ammo: g.ammo,
+                magazine: g.weapon.magazine,
+            }),
+            kills: score.kills,
+            game_over: game_state.game_over,
+        };
+
+        if !layout.visible {
+            return;
+        }
+        let health_color = match hud.health {
+            Some(health) if health <= layout.warning_health => layout.warning_color,
+            _ => layout.color,
+        };
+        Self::text(
+            &mut overlay,
+            &layout,
+            &layout.speed,
+            &hud.speed_text(),
+            layout.color,
+        );
+        Self::text(
+            &mut overlay,
+            &layout,
+            &layout.thrust,
+            &hud.thrust_text(),
+            layout.color,
+        );
+        Self::text(
+            &mut overlay,
+            &layout,
+            &layout.health,
+            &hud.health_text(),
+            health_color,
+        );
+        Self::text(
+            &mut overlay,
+            &layout,
+            &layout.target,
+            &hud.target_text(),
+            layout.color,
+        );
+        Self::text(
+            &mut overlay,
+            &layout,
+            &layout.score,
+            &hud.score_text(),
+            layout.color,
+        );
+        Self::text(
+            &mut overlay,
+            &layout,
+            &layout.weapon,
+            &hud.weapon_text(),
+            layout.color,
+        );
+        if layout.crosshair_size > 0.0 {
+            Self::crosshair(&mut overlay, &layout);
+        }
+    }
+}
@@ -1,10 +1,21 @@
+pub mod ai_system;
+pub mod ai_targeting_system;
 pub mod camera_system;
 pub mod collision_detector_3;
+pub mod console_system;
 pub mod damage_system;
 pub mod despawn_system;
+pub mod destruction_system;
 pub mod gl_system;
+pub mod homing_system;
+pub mod hud_system;
+pub mod particle_system;
 pub mod physics_system;
+pub mod player_input_system;
+pub mod profiler_system;
 pub mod pulsate_system;
+pub mod respawn_system;
+pub mod score_system;
 pub mod shoot_system;
 pub mod thruster_system;
 pub mod transformations_system;
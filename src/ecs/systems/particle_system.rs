@@ -0,0 +1,30 @@
+use crate::ecs::components::particle_emitter::ParticleEmitter;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::particles::Particles;
+use crate::ecs::resources::time_scale::TimeScale;
+use specs::prelude::*;
+
+pub struct ParticleSystem;
+
+impl<'a> System<'a> for ParticleSystem {
+    type SystemData = (
+        Write<'a, Particles>,
+        Read<'a, TimeScale>,
+        WriteStorage<'a, ParticleEmitter>,
+        ReadStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (mut particles, time_scale, mut emitter, transform): Self::SystemData) {
+        particles.update(time_scale.0);
+        for (e, t) in (&mut emitter, &transform).join() {
+            if !e.active {
+                e.carry = 0.0;
+                continue;
+            }
+            e.carry += e.rate * time_scale.0;
+            let count = e.carry.floor();
+            e.carry -= count;
+            particles.emit(e, count as usize, &t.position, &t.orientation);
+        }
+    }
+}
@@ -1,36 +1,50 @@
 use crate::ecs::components::physics::Physics;
 use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::debug_draw::{self, DebugDraw};
+use crate::ecs::resources::time_scale::TimeScale;
 use doem_math::Vector4;
 use specs::prelude::*;
 
+const VELOCITY_ARROW_SCALE: f32 = 10.0;
+
 pub struct PhysicsSystem;
 
 impl<'a> System<'a> for PhysicsSystem {
-    type SystemData = (WriteStorage<'a, Transform>, ReadStorage<'a, Physics>);
+    type SystemData = (
+        Write<'a, DebugDraw>,
+        Read<'a, TimeScale>,
+        WriteStorage<'a, Transform>,
+        ReadStorage<'a, Physics>,
+    );
 
-    fn run(&mut self, (mut transform, physics): Self::SystemData) {
+    fn run(&mut self, (mut debug_draw, time_scale, mut transform, physics): Self::SystemData) {
         for (t, p) in (&mut transform, &physics).join() {
+            let start = t.position.clone();
+            let velocity = &p.velocity * time_scale.0;
             {
                 let u = Vector4::from([[-1.0], [0.0], [0.0], [1.0]]);
                 let u_norm = u.normalize();
                 let u_rotated = &t.orientation * &u_norm;
-                let u_sped_up = &u_rotated * p.velocity[0][0];
+                let u_sped_up = &u_rotated * velocity[0][0];
                 t.position = &t.position + &u_sped_up.dimension_hop();
             }
             {
                 let u = Vector4::from([[0.0], [1.0], [0.0], [1.0]]);
                 let u_norm = u.normalize();
                 let u_rotated = &t.orientation * &u_norm;
-                let u_sped_up = &u_rotated * p.velocity[1][0];
+                let u_sped_up = &u_rotated * velocity[1][0];
                 t.position = &t.position + &u_sped_up.dimension_hop();
             }
             {
                 let u = Vector4::from([[0.0], [0.0], [1.0], [1.0]]);
                 let u_norm = u.normalize();
                 let u_rotated = &t.orientation * &u_norm;
-                let u_sped_up = &u_rotated * p.velocity[2][0];
+                let u_sped_up = &u_rotated * velocity[2][0];
                 t.position = &t.position + &u_sped_up.dimension_hop();
             }
+            // Exaggerated, the movement of a single tick is hard to see
+            let moved = &t.position - &start;
+            debug_draw.arrow(&start, &(&moved * VELOCITY_ARROW_SCALE), debug_draw::YELLOW);
         }
     }
 }
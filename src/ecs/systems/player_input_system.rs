@@ -0,0 +1,45 @@
+use crate::ecs::components::ai_pilot::AiPilot;
+use crate::ecs::components::fire_intent::FireIntent;
+use crate::ecs::components::gun::Gun;
+use crate::ecs::resources::doem_events::DoemEvents;
+use crate::weapons::Weapons;
+use luminance_glfw::{Action, Key, WindowEvent};
+use specs::prelude::*;
+
+// Turns the keyboard into intents for every entity without an AiPilot
+pub struct PlayerInputSystem;
+
+impl<'a> System<'a> for PlayerInputSystem {
+    type SystemData = (
+        Read<'a, DoemEvents>,
+        Read<'a, Weapons>,
+        ReadStorage<'a, AiPilot>,
+        WriteStorage<'a, FireIntent>,
+        WriteStorage<'a, Gun>,
+    );
+
+    fn run(&mut self, (events, weapons, ai_pilot, mut fire_intent, mut gun): Self::SystemData) {
+        let firing = events.0.iter().any(|e| match e {
+            WindowEvent::Key(Key::Space, _, action, _) => {
+                *action == Action::Press || *action == Action::Repeat
+            }
+            _ => false,
+        });
+        if firing {
+            for (intent, _) in (&mut fire_intent, !&ai_pilot).join() {
+                intent.firing = true;
+            }
+        }
+
+        // Switching starts with a full magazine of the next weapon
+        for e in &events.0 {
+            if let WindowEvent::Key(Key::Tab, _, Action::Press, _) = e {
+                for (g, _) in (&mut gun, !&ai_pilot).join() {
+                    if let Some(next) = weapons.next(&g.weapon.name) {
+                        *g = Gun::new(next.clone());
+                    }
+                }
+            }
+        }
+    }
+}
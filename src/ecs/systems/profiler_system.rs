@@ -0,0 +1,97 @@
+use crate::ecs::resources::asset_stats::AssetStats;
+use crate::ecs::resources::overlay::Overlay;
+use crate::ecs::resources::profiler::{Profiler, FRAME, HISTORY};
+use crate::ecs::resources::render_stats::RenderStats;
+use specs::prelude::*;
+
+// Screen pixels per font pixel
+const SCALE: f32 = 2.0;
+const MARGIN: f32 = 10.0;
+// Below the health in the HUD
+const TOP: f32 = 100.0;
+const GRAPH_HEIGHT: f32 = 100.0;
+const BAR_WIDTH: f32 = 2.0;
+// A frame at 60 fps
+const BUDGET_MILLIS: f32 = 1000.0 / 60.0;
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+const TEXT_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+const BAR_COLOR: [f32; 4] = [0.3, 0.9, 0.3, 0.9];
+const OVER_BUDGET_COLOR: [f32; 4] = [1.0, 0.3, 0.2, 0.9];
+const BUDGET_COLOR: [f32; 4] = [1.0, 1.0, 0.3, 0.6];
+
+// Draws the statistics of the Profiler, the render and asset counters of the last frame and a
+// graph of the frame times
+pub struct ProfilerSystem;
+
+impl<'a> System<'a> for ProfilerSystem {
+    type SystemData = (
+        Read<'a, Profiler>,
+        Read<'a, RenderStats>,
+        Read<'a, AssetStats>,
+        Write<'a, Overlay>,
+    );
+
+    fn run(&mut self, (profiler, render_stats, asset_stats, mut overlay): Self::SystemData) {
+        if !profiler.visible {
+            return;
+        }
+        let mut lines = vec![format!(
+            "{:<24}{:>7}{:>7}{:>7}{:>7}",
+            "ms", "min", "avg", "max", "p99"
+        )];
+        for name in profiler.names() {
+            let stats = profiler.stats(name);
+            lines.push(format!(
+                "{:<24}{:>7.2}{:>7.2}{:>7.2}{:>7.2}",
+                name, stats.min, stats.avg, stats.max, stats.p99
+            ));
+        }
+        lines.push(String::new());
+        lines.push(format!(
+            "entities drawn {} culled {}",
+            render_stats.drawn_entities, render_stats.culled_entities
+        ));
+        lines.push(format!(
+            "meshes {} cached {} boxes {} loading {}",
+            asset_stats.live_tesselations,
+            asset_stats.cached_meshes,
+            asset_stats.aabb_meshes,
+            asset_stats.pending_loads
+        ));
+        let text = lines.join("\n");
+        let (text_width, text_height) = Overlay::text_size(&text, SCALE);
+        let graph_width = HISTORY as f32 * BAR_WIDTH;
+        let width = text_width.max(graph_width) + MARGIN * 2.0;
+        let height = text_height + GRAPH_HEIGHT + MARGIN * 3.0;
+        overlay.rect(MARGIN, TOP, width, height, BACKGROUND_COLOR);
+        overlay.text(&text, MARGIN * 2.0, TOP + MARGIN, SCALE, TEXT_COLOR);
+
+        // Two frame budgets fill the graph, longer frames are cut off
+        let bottom = TOP + text_height + MARGIN * 2.0 + GRAPH_HEIGHT;
+        let pixels_per_milli = GRAPH_HEIGHT / (BUDGET_MILLIS * 2.0);
+        if let Some(frames) = profiler.samples(FRAME) {
+            for (i, millis) in frames.iter().enumerate() {
+                let bar_height = (millis * pixels_per_milli).min(GRAPH_HEIGHT);
+                let color = if *millis > BUDGET_MILLIS {
+                    OVER_BUDGET_COLOR
+                } else {
+                    BAR_COLOR
+                };
+                overlay.rect(
+                    MARGIN * 2.0 + i as f32 * BAR_WIDTH,
+                    bottom - bar_height,
+                    BAR_WIDTH,
+                    bar_height,
+                    color,
+                );
+            }
+        }
+        overlay.rect(
+            MARGIN * 2.0,
+            bottom - BUDGET_MILLIS * pixels_per_milli,
+            graph_width,
+            1.0,
+            BUDGET_COLOR,
+        );
+    }
+}
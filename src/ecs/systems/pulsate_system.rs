@@ -1,31 +1,37 @@
 use crate::ecs::components::pulsate::Pulsate;
 use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::time_scale::TimeScale;
 use specs::prelude::*;
 
 pub struct PulsateSystem;
 
 impl<'a> System<'a> for PulsateSystem {
-    type SystemData = (WriteStorage<'a, Pulsate>, WriteStorage<'a, Transform>);
+    type SystemData = (
+        Read<'a, TimeScale>,
+        WriteStorage<'a, Pulsate>,
+        WriteStorage<'a, Transform>,
+    );
 
-    fn run(&mut self, (mut pulsate, mut transform): Self::SystemData) {
+    fn run(&mut self, (time_scale, mut pulsate, mut transform): Self::SystemData) {
         for (p, t) in (&mut pulsate, &mut transform).join() {
+            let speed = &p.speed * time_scale.0;
             // Growing bigger
             if p.current_direction {
                 // It will become too big
-                if t.scale.sign_length() + p.speed.length() > p.max_scale.length() {
-                    t.scale = &p.max_scale + &(&p.max_scale - &(&t.scale + &p.speed));
+                if t.scale.sign_length() + speed.length() > p.max_scale.length() {
+                    t.scale = &p.max_scale + &(&p.max_scale - &(&t.scale + &speed));
                     p.current_direction = !p.current_direction;
                 } else {
-                    t.scale = &t.scale + &p.speed;
+                    t.scale = &t.scale + &speed;
                 }
             } else {
                 // Shrinking
                 // It will become too small
-                if t.scale.sign_length() - p.speed.length() < p.min_scale.sign_length() {
-                    t.scale = &p.min_scale - &(&p.min_scale - &(&t.scale - &p.speed));
+                if t.scale.sign_length() - speed.length() < p.min_scale.sign_length() {
+                    t.scale = &p.min_scale - &(&p.min_scale - &(&t.scale - &speed));
                     p.current_direction = !p.current_direction;
                 } else {
-                    t.scale = &t.scale - &p.speed;
+                    t.scale = &t.scale - &speed;
                 }
             }
         }
@@ -0,0 +1,96 @@
+use crate::ecs::components::camera::Camera;
+use crate::ecs::components::health::Health;
+use crate::ecs::components::invulnerable::Invulnerable;
+use crate::ecs::components::lives::Lives;
+use crate::ecs::components::particle_emitter::ParticleEmitter;
+use crate::ecs::components::physics::Physics;
+use crate::ecs::components::shield::Shield;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::destructions::Destructions;
+use crate::ecs::resources::game_state::{GameState, RespawnPoint};
+use crate::ecs::resources::particles::Particles;
+use doem_math::Vector3;
+use specs::prelude::*;
+
+// Ticks nothing can damage the player after respawning
+const RESPAWN_INVULNERABILITY: f32 = 180.0;
+
+// Takes what still has lives out of the Destructions and brings it back at the RespawnPoint,
+// losing the Camera for good is game over
+pub struct RespawnSystem;
+
+impl<'a> System<'a> for RespawnSystem {
+    type SystemData = (
+        Write<'a, Destructions>,
+        Write<'a, GameState>,
+        Write<'a, Particles>,
+        Read<'a, RespawnPoint>,
+        WriteStorage<'a, Lives>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Physics>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, Shield>,
+        WriteStorage<'a, Invulnerable>,
+        ReadStorage<'a, Camera>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut destructions,
+            mut game_state,
+            mut particles,
+            respawn_point,
+            mut lives,
+            mut transform,
+            mut physics,
+            mut health,
+            mut shield,
+            mut invulnerable,
+            camera,
+        ): Self::SystemData,
+    ) {
+        let mut respawned: Vec<(Entity, f32)> = Vec::new();
+        for d in &destructions.destroyed {
+            match lives.get_mut(d.entity) {
+                Some(l) if l.remaining > 0 => {
+                    l.remaining -= 1;
+                    info!("Lost a life, {} left", l.remaining);
+                    respawned.push((d.entity, l.health));
+                }
+                _ if camera.contains(d.entity) => {
+                    info!("Game over");
+                    game_state.game_over = true;
+                }
+                _ => (),
+            }
+        }
+        destructions
+            .destroyed
+            .retain(|d| !respawned.iter().any(|(e, _)| *e == d.entity));
+
+        for (e, full_health) in respawned {
+            if let Some(t) = transform.get_mut(e) {
+                particles.burst(&ParticleEmitter::explosion(), &t.position, &t.orientation);
+                t.position = respawn_point.position.clone();
+                t.orientation = respawn_point.orientation.clone();
+            }
+            if let Some(p) = physics.get_mut(e) {
+                p.velocity = Vector3::from([[0.0], [0.0], [0.0]]);
+            }
+            if let Some(h) = health.get_mut(e) {
+                h.health = full_health;
+            }
+            if let Some(s) = shield.get_mut(e) {
+                s.current = s.max;
+                s.since_hit = s.delay;
+            }
+            invulnerable.insert(
+                e,
+                Invulnerable {
+                    ticks: RESPAWN_INVULNERABILITY,
+                },
+            );
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use crate::ecs::components::camera::Camera;
+use crate::ecs::resources::destructions::Destructions;
+use crate::ecs::resources::score::Score;
+use specs::prelude::*;
+
+// Counts what the player destroyed
+pub struct ScoreSystem;
+
+impl<'a> System<'a> for ScoreSystem {
+    type SystemData = (
+        Read<'a, Destructions>,
+        Write<'a, Score>,
+        ReadStorage<'a, Camera>,
+    );
+
+    fn run(&mut self, (destructions, mut score, camera): Self::SystemData) {
+        for d in &destructions.destroyed {
+            let by_player = d.killer.map_or(false, |killer| camera.contains(killer));
+            if by_player && d.killer != Some(d.entity) {
+                score.kills += 1;
+            }
+        }
+    }
+}
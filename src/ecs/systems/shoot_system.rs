@@ -1,22 +1,74 @@
-use crate::consts;
+use crate::ecs::components::camera::Camera;
 use crate::ecs::components::collider::Collider;
 use crate::ecs::components::damage::Damage;
 use crate::ecs::components::despawn::Despawn;
+use crate::ecs::components::fire_intent::FireIntent;
 use crate::ecs::components::gun::Gun;
+use crate::ecs::components::health::Health;
+use crate::ecs::components::homing::Homing;
+use crate::ecs::components::lifetime::Lifetime;
+use crate::ecs::components::particle_emitter::ParticleEmitter;
 use crate::ecs::components::physics::Physics;
 use crate::ecs::components::shape::Shape;
 use crate::ecs::components::transform::Transform;
-use crate::ecs::resources::doem_events::DoemEvents;
-use doem_math::Vector3;
-use luminance_glfw::{Action, Key, WindowEvent};
+use crate::ecs::resources::particles::Particles;
+use crate::ecs::resources::time_scale::TimeScale;
+use crate::ecs::systems::ai_system::AiSystem;
+use doem_math::{Matrix4, Vector3};
 use specs::prelude::*;
 
+// Homing projectiles lock on to the target closest to the nose within this angle, in radians
+const HOMING_CONE: f32 = 0.5;
+
+struct Projectile {
+    shape: Shape,
+    damage: Damage,
+    transform: Transform,
+    physics: Physics,
+    homing: Option<Homing>,
+    lifetime: Option<Lifetime>,
+}
+
+// Fires the Gun of every entity whose FireIntent is set, as far as its fire rate and magazine
+// allow
 pub struct ShootSystem;
 
+impl ShootSystem {
+    // Something which can be damaged, or the player
+    fn homing_target(
+        shooter: Entity,
+        t: &Transform,
+        targets: &[(Entity, Vector3)],
+    ) -> Option<Entity> {
+        let forward = AiSystem::forward(&t.orientation);
+        let min_cos = HOMING_CONE.cos();
+        targets
+            .iter()
+            .filter(|(e, _)| *e != shooter)
+            .filter_map(|(e, position)| {
+                let to_target = position - &t.position;
+                let length = to_target.length();
+                if length == 0.0 {
+                    return None;
+                }
+                let cos = forward.dot_product(&to_target) / length;
+                if cos >= min_cos {
+                    Some((*e, cos))
+                } else {
+                    None
+                }
+            })
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(e, _)| e)
+    }
+}
+
 impl<'a> System<'a> for ShootSystem {
     type SystemData = (
-        Read<'a, DoemEvents>,
-        ReadStorage<'a, Gun>,
+        Read<'a, TimeScale>,
+        Write<'a, Particles>,
+        WriteStorage<'a, Gun>,
+        WriteStorage<'a, FireIntent>,
         Entities<'a>,
         WriteStorage<'a, Shape>,
         WriteStorage<'a, Damage>,
@@ -24,13 +76,20 @@ impl<'a> System<'a> for ShootSystem {
         WriteStorage<'a, Transform>,
         WriteStorage<'a, Physics>,
         WriteStorage<'a, Despawn>,
+        WriteStorage<'a, ParticleEmitter>,
+        WriteStorage<'a, Homing>,
+        WriteStorage<'a, Lifetime>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Camera>,
     );
 
     fn run(
         &mut self,
         (
-            events,
-            gun,
+            time_scale,
+            mut particles,
+            mut gun,
+            mut fire_intent,
             entities,
             mut shape,
             mut damage,
@@ -38,52 +97,197 @@ impl<'a> System<'a> for ShootSystem {
             mut transform,
             mut physics,
             mut despawn,
+            mut emitter,
+            mut homing,
+            mut lifetime,
+            health,
+            camera,
         ): Self::SystemData,
     ) {
-        let mut to_add: Vec<(Shape, Damage, Collider, Transform, Physics, Despawn)> = Vec::new();
-        for (ent, shooter_t, shooter_p, shooter_g) in
-            (&*entities, &transform, &physics, &gun).join()
+        let targets: Vec<(Entity, Vector3)> = (
+            &*entities,
+            &transform,
+            (&health).maybe(),
+            (&camera).maybe(),
+            !&damage,
+        )
+            .join()
+            .filter(|(_, _, h, c, _)| h.is_some() || c.is_some())
+            .map(|(e, t, ..)| (e, t.position.clone()))
+            .collect();
+
+        let mut to_add: Vec<Projectile> = Vec::new();
+        for (ent, shooter_t, shooter_p, shooter_g, intent) in (
+            &*entities,
+            &transform,
+            &physics,
+            &mut gun,
+            (&mut fire_intent).maybe(),
+        )
+            .join()
         {
-            for event in &events.0 {
-                match event {
-                    WindowEvent::Key(Key::Space, _, action, _)
-                        if *action == Action::Press || *action == Action::Repeat =>
-                    {
-                        to_add.push((
-                            Shape::Unit {
-                                obj_path: consts::BULLET_OBJ_PATH.to_owned(),
-                            },
-                            Damage {
-                                damage: shooter_g.damage,
-                                despawn_entity_on_impact: shooter_g.despawn_bullet_on_impact,
-                                damage_dealer: ent,
-                            },
-                            Collider {
-                                half_size: Vector3::from([[0.0], [0.0], [0.0]]),
-                            },
-                            Transform {
-                                position: shooter_t.position.clone(),
-                                scale: consts::BULLET_SCALE.clone(),
-                                orientation: shooter_t.orientation.clone(),
-                            },
-                            Physics {
-                                velocity: &shooter_p.velocity + &shooter_g.velocity,
-                            },
-                            Despawn {},
-                        ));
-                    }
-                    _ => (),
+            shooter_g.cooldown = (shooter_g.cooldown - time_scale.0).max(0.0);
+            if shooter_g.weapon.magazine > 0 && shooter_g.ammo == 0 {
+                shooter_g.reloading -= time_scale.0;
+                if shooter_g.reloading <= 0.0 {
+                    shooter_g.reloading = 0.0;
+                    shooter_g.ammo = shooter_g.weapon.magazine;
+                }
+            }
+            let triggered = match intent {
+                Some(intent) => {
+                    let triggered = intent.firing;
+                    intent.firing = false;
+                    triggered
+                }
+                None => false,
+            };
+            if !triggered || !shooter_g.ready() {
+                continue;
+            }
+
+            let weapon = &shooter_g.weapon;
+            particles.burst(
+                &ParticleEmitter::muzzle_flash(),
+                &shooter_t.position,
+                &shooter_t.orientation,
+            );
+            let target = if weapon.homing > 0.0 {
+                Self::homing_target(ent, shooter_t, &targets)
+            } else {
+                None
+            };
+            let barrels = shooter_g.barrels();
+            for barrel in 0..barrels {
+                // Fanned out around the local up axis, the middle barrel fires straight ahead
+                let angle = (barrel as f32 - (barrels as f32 - 1.0) / 2.0) * weapon.spread;
+                to_add.push(Projectile {
+                    shape: Shape::Unit {
+                        obj_path: weapon.projectile.clone(),
+                    },
+                    damage: Damage {
+                        damage: weapon.damage,
+                        damage_type: weapon.damage_type,
+                        despawn_entity_on_impact: weapon.despawn_on_impact,
+                        damage_dealer: ent,
+                    },
+                    transform: Transform {
+                        position: shooter_t.position.clone(),
+                        scale: Vector3::from([
+                            [weapon.projectile_scale],
+                            [weapon.projectile_scale],
+                            [weapon.projectile_scale],
+                        ]),
+                        orientation: &shooter_t.orientation * &Matrix4::get_rotation_y(angle),
+                    },
+                    physics: Physics {
+                        velocity: &shooter_p.velocity
+                            + &Vector3::from([[weapon.speed], [0.0], [0.0]]),
+                    },
+                    homing: target.map(|target| Homing {
+                        target,
+                        turn_rate: weapon.homing,
+                    }),
+                    lifetime: weapon.lifetime(),
+                });
+            }
+            shooter_g.cooldown = weapon.fire_interval;
+            if weapon.magazine > 0 {
+                shooter_g.ammo -= barrels;
+                if shooter_g.ammo == 0 {
+                    shooter_g.reloading = weapon.reload_time;
                 }
             }
         }
-        for comps in to_add {
+        for projectile in to_add {
             let bullet = entities.create();
-            shape.insert(bullet, comps.0);
-            damage.insert(bullet, comps.1);
-            collider.insert(bullet, comps.2);
-            transform.insert(bullet, comps.3);
-            physics.insert(bullet, comps.4);
-            despawn.insert(bullet, comps.5);
+            let trail = if projectile.homing.is_some() {
+                ParticleEmitter::thrust()
+            } else {
+                ParticleEmitter::bullet_trail()
+            };
+            shape.insert(bullet, projectile.shape);
+            damage.insert(bullet, projectile.damage);
+            collider.insert(
+                bullet,
+                Collider {
+                    half_size: Vector3::from([[0.0], [0.0], [0.0]]),
+                },
+            );
+            transform.insert(bullet, projectile.transform);
+            physics.insert(bullet, projectile.physics);
+            despawn.insert(bullet, Despawn {});
+            emitter.insert(bullet, trail);
+            if let Some(h) = projectile.homing {
+                homing.insert(bullet, h);
+            }
+            if let Some(l) = projectile.lifetime {
+                lifetime.insert(bullet, l);
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::world::DoemWorld;
+    use crate::weapons::Weapon;
+
+    // A scattergun with 5 barrels and 6 rounds, trying to fire every tick
+    fn scattergun_world() -> (World, Entity) {
+        let mut world = DoemWorld::new();
+        System::setup(&mut ShootSystem, &mut world);
+        let mut weapon = Weapon::new("scattergun");
+        weapon.barrels = 5;
+        weapon.magazine = 6;
+        weapon.fire_interval = 0.0;
+        weapon.reload_time = 100.0;
+        let shooter = world
+            .create_entity()
+            .with(Transform {
+                position: Vector3::origin(),
+                scale: Vector3::from([[1.0], [1.0], [1.0]]),
+                orientation: Matrix4::identity(),
+            })
+            .with(Physics {
+                velocity: Vector3::origin(),
+            })
+            .with(Gun::new(weapon))
+            .with(FireIntent::default())
+            .build();
+        (world, shooter)
+    }
+
+    // The number of projectiles fired this tick
+    fn fire(world: &mut World, shooter: Entity) -> usize {
+        let before = world.read_storage::<Damage>().count();
+        world
+            .write_storage::<FireIntent>()
+            .get_mut(shooter)
+            .unwrap()
+            .firing = true;
+        ShootSystem.run_now(world);
+        world.maintain();
+        world.read_storage::<Damage>().count() - before
+    }
+
+    #[test]
+    fn last_rounds_fire_fewer_barrels() {
+        let (mut world, shooter) = scattergun_world();
+        assert_eq!(fire(&mut world, shooter), 5);
+        assert_eq!(fire(&mut world, shooter), 1);
+        let gun = world.read_storage::<Gun>();
+        let gun = gun.get(shooter).unwrap();
+        assert_eq!(gun.ammo, 0);
+        assert_eq!(gun.reloading, 100.0);
+    }
+
+    #[test]
+    fn nothing_fires_while_reloading() {
+        let (mut world, shooter) = scattergun_world();
+        fire(&mut world, shooter);
+        fire(&mut world, shooter);
+        assert_eq!(fire(&mut world, shooter), 0);
+    }
+}
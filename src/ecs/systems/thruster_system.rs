@@ -1,3 +1,5 @@
+use crate::ecs::components::ai_pilot::AiPilot;
+use crate::ecs::components::particle_emitter::ParticleEmitter;
 use crate::ecs::components::physics::Physics;
 use crate::ecs::components::thruster::Thruster;
 use crate::ecs::resources::doem_events::DoemEvents;
@@ -12,34 +14,41 @@ impl<'a> System<'a> for ThrusterSystem {
         Read<'a, DoemEvents>,
         WriteStorage<'a, Physics>,
         ReadStorage<'a, Thruster>,
+        ReadStorage<'a, AiPilot>,
+        WriteStorage<'a, ParticleEmitter>,
     );
 
-    fn run(&mut self, (events, mut physics, thruster): Self::SystemData) {
+    // Pilots fly their own ships, the keyboard only drives the rest
+    fn run(&mut self, (events, mut physics, thruster, ai_pilot, mut emitter): Self::SystemData) {
         for e in &events.0 {
             match e {
                 WindowEvent::Key(Key::LeftShift, _, action, _)
                     if *action == Action::Press || *action == Action::Repeat =>
                 {
-                    for (p, t) in (&mut physics, &thruster).join() {
+                    for (p, t, _) in (&mut physics, &thruster, !&ai_pilot).join() {
                         p.velocity = &p.velocity + &t.power;
                     }
                 }
                 WindowEvent::Key(Key::LeftControl, _, action, _)
                     if *action == Action::Press || *action == Action::Repeat =>
                 {
-                    for (p, t) in (&mut physics, &thruster).join() {
+                    for (p, t, _) in (&mut physics, &thruster, !&ai_pilot).join() {
                         p.velocity = &p.velocity - &t.power;
                     }
                 }
                 WindowEvent::Key(Key::N, _, action, _)
                     if *action == Action::Press || *action == Action::Repeat =>
                 {
-                    for (p, _t) in (&mut physics, &thruster).join() {
+                    for (p, _t, _) in (&mut physics, &thruster, !&ai_pilot).join() {
                         p.velocity = Vector3::from([[0.0], [0.0], [0.0]]);
                     }
                 }
                 _ => (),
             }
         }
+        // The exhaust burns as long as the engine is on
+        for (p, _t, e) in (&physics, &thruster, &mut emitter).join() {
+            e.active = p.velocity.length() > 0.0;
+        }
     }
 }
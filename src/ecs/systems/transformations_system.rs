@@ -112,7 +112,7 @@ impl TransformationsSystem {
             let x_norm = x.normalize();
             return orientation * &Matrix4::get_rotation(&x_norm, angle);
         }
-        Matrix4::identity()
+        orientation.clone()
     }
     pub fn rotate_y(orientation: &Matrix4, shape: &Shape, angle: Scalar) -> Matrix4 {
         if let Shape::Init { bounding_box, .. } = shape {
@@ -125,7 +125,7 @@ impl TransformationsSystem {
             let y_norm = y.normalize();
             return orientation * &Matrix4::get_rotation(&y_norm, angle);
         }
-        Matrix4::identity()
+        orientation.clone()
     }
     pub fn rotate_z(orientation: &Matrix4, shape: &Shape, angle: Scalar) -> Matrix4 {
         if let Shape::Init { bounding_box, .. } = shape {
@@ -138,6 +138,6 @@ impl TransformationsSystem {
             let z_norm = z.normalize();
             return orientation * &Matrix4::get_rotation(&z_norm, angle);
         }
-        Matrix4::identity()
+        orientation.clone()
     }
 }
@@ -1,12 +1,22 @@
+use crate::ecs::components::ai_pilot::AiPilot;
+use crate::ecs::components::armor::Armor;
 use crate::ecs::components::camera::Camera;
 use crate::ecs::components::collider::Collider;
 use crate::ecs::components::damage::Damage;
 use crate::ecs::components::despawn::Despawn;
+use crate::ecs::components::fire_intent::FireIntent;
 use crate::ecs::components::gun::Gun;
 use crate::ecs::components::health::Health;
+use crate::ecs::components::homing::Homing;
+use crate::ecs::components::invulnerable::Invulnerable;
+use crate::ecs::components::level_of_detail::LevelOfDetail;
+use crate::ecs::components::lifetime::Lifetime;
+use crate::ecs::components::lives::Lives;
+use crate::ecs::components::particle_emitter::ParticleEmitter;
 use crate::ecs::components::physics::Physics;
 use crate::ecs::components::pulsate::Pulsate;
 use crate::ecs::components::shape::Shape;
+use crate::ecs::components::shield::Shield;
 use crate::ecs::components::thruster::Thruster;
 use crate::ecs::components::transform::Transform;
 use crate::ecs::components::transformable::Transformable;
@@ -29,6 +39,16 @@ impl DoemWorld {
         world.register::<Gun>();
         world.register::<Thruster>();
         world.register::<Despawn>();
+        world.register::<LevelOfDetail>();
+        world.register::<ParticleEmitter>();
+        world.register::<AiPilot>();
+        world.register::<FireIntent>();
+        world.register::<Homing>();
+        world.register::<Lifetime>();
+        world.register::<Shield>();
+        world.register::<Armor>();
+        world.register::<Invulnerable>();
+        world.register::<Lives>();
 
         world
     }
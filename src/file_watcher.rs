@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Polls the modification times of the files in a set of directories, no subdirectories
+pub struct FileWatcher {
+    dirs: Vec<PathBuf>,
+    modified: HashMap<PathBuf, SystemTime>,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new<P>(dirs: &[P]) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        let mut watcher = Self {
+            dirs: dirs.iter().map(|d| d.as_ref().to_path_buf()).collect(),
+            modified: HashMap::<PathBuf, SystemTime>::new(),
+            last_poll: Instant::now(),
+        };
+        // Take a snapshot, only changes made after this should be reported
+        watcher.scan();
+        watcher
+    }
+    // Returns the files which changed since the last poll
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+        self.scan()
+    }
+    fn scan(&mut self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = Vec::new();
+        for dir in &self.dirs {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    warn!("Could not watch {}: {}", dir.display(), e);
+                    continue;
+                }
+            };
+            for entry in entries.filter_map(Result::ok) {
+                let path = entry.path();
+                let modified = match entry.metadata().and_then(|m| m.modified()) {
+                    Ok(modified) => modified,
+                    Err(_) => continue,
+                };
+                if self.modified.get(&path) != Some(&modified) {
+                    self.modified.insert(path.clone(), modified);
+                    changed.push(path);
+                }
+            }
+        }
+        changed
+    }
+}
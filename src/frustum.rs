@@ -0,0 +1,174 @@
+use crate::data::AABB;
+use doem_math::{Matrix4, Vector3};
+
+pub struct Plane {
+    pub normal: Vector3,
+    pub distance: f32,
+}
+
+impl Plane {
+    fn from_row(row: [f32; 4]) -> Self {
+        let normal = Vector3::from([[row[0]], [row[1]], [row[2]]]);
+        let length = normal.length();
+        Self {
+            normal: &normal * (1.0 / length),
+            distance: row[3] / length,
+        }
+    }
+    // Positive in front of the plane, negative behind it
+    pub fn signed_distance(&self, point: &Vector3) -> f32 {
+        self.normal.dot_product(point) + self.distance
+    }
+}
+
+// The left, right, bottom, top, near and far planes, all facing inwards
+pub struct Frustum {
+    pub planes: Vec<Plane>,
+}
+
+impl Frustum {
+    // Gribb and Hartmann: every plane is the sum or difference of the last row with one of the
+    // other rows of the projection * view matrix
+    pub fn from_matrix(m: &Matrix4) -> Self {
+        let row = |i: usize| [m[i][0], m[i][1], m[i][2], m[i][3]];
+        let w = row(3);
+        let mut planes: Vec<Plane> = Vec::with_capacity(6);
+        for i in 0..3 {
+            let r = row(i);
+            planes.push(Plane::from_row([
+                w[0] + r[0],
+                w[1] + r[1],
+                w[2] + r[2],
+                w[3] + r[3],
+            ]));
+            planes.push(Plane::from_row([
+                w[0] - r[0],
+                w[1] - r[1],
+                w[2] - r[2],
+                w[3] - r[3],
+            ]));
+        }
+        Self { planes }
+    }
+    // Conservative, a box near a corner of the frustum can be reported as visible
+    pub fn intersects(&self, aabb: &AABB) -> bool {
+        for plane in &self.planes {
+            let radius = plane.normal[0][0].abs() * aabb.half_size[0][0]
+                + plane.normal[1][0].abs() * aabb.half_size[1][0]
+                + plane.normal[2][0].abs() * aabb.half_size[2][0];
+            if plane.signed_distance(&aabb.middle_point) < -radius {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use doem_math::PI;
+
+    const NEAR: f32 = 1.0;
+    const FAR: f32 = 100.0;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 0.0001 * expected.abs().max(1.0),
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    // A square view of 90 degrees from the eye towards the look at point
+    fn frustum(eye: [f32; 3], look_at: [f32; 3]) -> Frustum {
+        let vector = |v: [f32; 3]| Vector3::from([[v[0]], [v[1]], [v[2]]]);
+        let projection = Matrix4::get_projection(PI / 2.0, 1.0, NEAR, FAR);
+        let view = Matrix4::get_view(
+            &vector(eye),
+            &vector(look_at),
+            &Vector3::from([[0.0], [1.0], [0.0]]),
+        );
+        Frustum::from_matrix(&(&projection * &view))
+    }
+
+    fn point(x: f32, y: f32, z: f32) -> AABB {
+        AABB {
+            middle_point: Vector3::from([[x], [y], [z]]),
+            half_size: Vector3::origin(),
+        }
+    }
+
+    #[test]
+    fn planes_are_normalised() {
+        for plane in &frustum([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]).planes {
+            assert_close(plane.normal.length(), 1.0);
+        }
+    }
+
+    #[test]
+    fn near_and_far_planes() {
+        let f = frustum([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]);
+        let near = &f.planes[4];
+        assert_close(near.normal[2][0], -1.0);
+        assert_close(near.distance, -NEAR);
+        let far = &f.planes[5];
+        assert_close(far.normal[2][0], 1.0);
+        assert_close(far.distance, FAR);
+    }
+
+    #[test]
+    fn side_planes_meet_at_the_eye() {
+        let f = frustum([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]);
+        let half = std::f32::consts::FRAC_1_SQRT_2;
+        let left = &f.planes[0];
+        assert_close(left.normal[0][0], half);
+        assert_close(left.normal[2][0], -half);
+        assert_close(left.distance, 0.0);
+        let top = &f.planes[3];
+        assert_close(top.normal[1][0], -half);
+        assert_close(top.normal[2][0], -half);
+        assert_close(top.distance, 0.0);
+    }
+
+    #[test]
+    fn points_inside_behind_and_past_the_far_plane() {
+        let f = frustum([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]);
+        assert!(f.intersects(&point(0.0, 0.0, -10.0)));
+        assert!(f.intersects(&point(5.0, -5.0, -10.0)));
+        assert!(!f.intersects(&point(0.0, 0.0, 10.0)));
+        assert!(!f.intersects(&point(0.0, 0.0, -0.5)));
+        assert!(!f.intersects(&point(0.0, 0.0, -FAR - 1.0)));
+        assert!(!f.intersects(&point(-20.0, 0.0, -10.0)));
+        assert!(!f.intersects(&point(0.0, 20.0, -10.0)));
+    }
+
+    #[test]
+    fn boxes_sticking_into_the_frustum_intersect() {
+        let f = frustum([0.0, 0.0, 0.0], [0.0, 0.0, -1.0]);
+        let straddling = AABB {
+            middle_point: Vector3::from([[-12.0], [0.0], [-10.0]]),
+            half_size: Vector3::from([[3.0], [3.0], [3.0]]),
+        };
+        assert!(f.intersects(&straddling));
+        let past_far = AABB {
+            middle_point: Vector3::from([[0.0], [0.0], [-FAR - 5.0]]),
+            half_size: Vector3::from([[1.0], [1.0], [10.0]]),
+        };
+        assert!(f.intersects(&past_far));
+        let behind = AABB {
+            middle_point: Vector3::from([[0.0], [0.0], [5.0]]),
+            half_size: Vector3::from([[1.0], [1.0], [1.0]]),
+        };
+        assert!(!f.intersects(&behind));
+    }
+
+    #[test]
+    fn follows_the_view() {
+        let f = frustum([0.0, 0.0, 50.0], [0.0, 0.0, 0.0]);
+        assert!(f.intersects(&point(0.0, 0.0, 0.0)));
+        assert!(!f.intersects(&point(0.0, 0.0, 60.0)));
+        assert!(!f.intersects(&point(0.0, 0.0, 50.0 - FAR - 1.0)));
+    }
+}
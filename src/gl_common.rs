@@ -1,4 +1,8 @@
+use doem_math::Matrix4;
+use luminance::pipeline::BoundTexture;
+use luminance::pixel::NormUnsigned;
 use luminance::shader::program::Uniform;
+use luminance::texture::{Cubemap, Dim2, Flat};
 use luminance_derive::{Semantics, UniformInterface, Vertex};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Semantics)]
@@ -7,6 +11,15 @@ pub enum VertexSemantics {
     VertexPosition,
     #[sem(name = "color", repr = "[f32; 3]", wrapper = "VertexColor")]
     Color,
+    // The columns of the per instance transformation matrix
+    #[sem(name = "model_x", repr = "[f32; 4]", wrapper = "ModelX")]
+    ModelX,
+    #[sem(name = "model_y", repr = "[f32; 4]", wrapper = "ModelY")]
+    ModelY,
+    #[sem(name = "model_z", repr = "[f32; 4]", wrapper = "ModelZ")]
+    ModelZ,
+    #[sem(name = "model_w", repr = "[f32; 4]", wrapper = "ModelW")]
+    ModelW,
 }
 
 #[repr(C)]
@@ -18,15 +31,137 @@ pub struct Vertex {
     pub color: VertexColor,
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Vertex)]
+#[vertex(sem = "VertexSemantics", instanced = "true")]
+pub struct Instance {
+    pub model_x: ModelX,
+    pub model_y: ModelY,
+    pub model_z: ModelZ,
+    pub model_w: ModelW,
+}
+
+impl Instance {
+    pub fn from_transform(transform: &Matrix4) -> Self {
+        // GL wants the matrix column by column
+        let columns: [[f32; 4]; 4] = transform.transpose().into();
+        Self {
+            model_x: ModelX::new(columns[0]),
+            model_y: ModelY::new(columns[1]),
+            model_z: ModelZ::new(columns[2]),
+            model_w: ModelW::new(columns[3]),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Semantics)]
+pub enum ParticleSemantics {
+    // A corner of the billboard, between -1 and 1
+    #[sem(name = "corner", repr = "[f32; 2]", wrapper = "ParticleCorner")]
+    Corner,
+    #[sem(name = "center", repr = "[f32; 3]", wrapper = "ParticleCenter")]
+    Center,
+    #[sem(name = "color", repr = "[f32; 4]", wrapper = "ParticleColor")]
+    Color,
+    #[sem(name = "size", repr = "f32", wrapper = "ParticleSize")]
+    Size,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Vertex)]
+#[vertex(sem = "ParticleSemantics")]
+pub struct ParticleVertex {
+    pub corner: ParticleCorner,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Vertex)]
+#[vertex(sem = "ParticleSemantics", instanced = "true")]
+pub struct ParticleInstance {
+    pub center: ParticleCenter,
+    pub color: ParticleColor,
+    pub size: ParticleSize,
+}
+
+impl ParticleInstance {
+    pub fn from_values(center: [f32; 3], color: [f32; 4], size: f32) -> Self {
+        Self {
+            center: ParticleCenter::new(center),
+            color: ParticleColor::new(color),
+            size: ParticleSize::new(size),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Semantics)]
+pub enum OverlaySemantics {
+    // A corner of the rectangle, between -1 and 1
+    #[sem(name = "corner", repr = "[f32; 2]", wrapper = "OverlayCorner")]
+    Corner,
+    // x, y, width and height in pixels
+    #[sem(name = "rect", repr = "[f32; 4]", wrapper = "OverlayRect")]
+    Rect,
+    #[sem(name = "color", repr = "[f32; 4]", wrapper = "OverlayColor")]
+    Color,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Vertex)]
+#[vertex(sem = "OverlaySemantics")]
+pub struct OverlayVertex {
+    pub corner: OverlayCorner,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Vertex)]
+#[vertex(sem = "OverlaySemantics", instanced = "true")]
+pub struct OverlayInstance {
+    pub rect: OverlayRect,
+    pub color: OverlayColor,
+}
+
 // Create a uniform interface. This is a type that will be used to customize the shader. In our
 // case, we just want to pass the time and the position of the triangle, for instance.
 //
 // This macro only supports structs for now; you cannot use enums as uniform interfaces.
 #[derive(Debug, UniformInterface)]
 pub struct ShaderInterface {
-    pub transform: Uniform<[[f32; 4]; 4]>,
     #[uniform(unbound)]
     pub projection: Uniform<[[f32; 4]; 4]>,
     #[uniform(unbound)]
     pub view: Uniform<[[f32; 4]; 4]>,
 }
+
+// Used to copy the offscreen framebuffer onto the back buffer
+#[derive(UniformInterface)]
+pub struct CopyInterface {
+    pub source: Uniform<&'static BoundTexture<'static, Flat, Dim2, NormUnsigned>>,
+}
+
+#[derive(UniformInterface)]
+pub struct SkyboxInterface {
+    pub projection: Uniform<[[f32; 4]; 4]>,
+    pub view: Uniform<[[f32; 4]; 4]>,
+    pub skybox: Uniform<&'static BoundTexture<'static, Flat, Cubemap, NormUnsigned>>,
+}
+
+#[derive(UniformInterface)]
+pub struct StarfieldInterface {
+    pub projection: Uniform<[[f32; 4]; 4]>,
+    pub view: Uniform<[[f32; 4]; 4]>,
+    pub eye: Uniform<[f32; 3]>,
+    pub stars_per_layer: Uniform<i32>,
+    pub layer_size: Uniform<f32>,
+    pub brightness: Uniform<f32>,
+}
+
+#[derive(UniformInterface)]
+pub struct ParticleInterface {
+    pub projection: Uniform<[[f32; 4]; 4]>,
+    pub view: Uniform<[[f32; 4]; 4]>,
+}
+
+#[derive(UniformInterface)]
+pub struct OverlayInterface {
+    pub screen_size: Uniform<[f32; 2]>,
+}
@@ -0,0 +1,107 @@
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::str::FromStr;
+use std::time::Instant;
+
+// Read when --log is not given
+pub const LOG_ENV: &str = "DOEM_LOG";
+pub const DEFAULT_FILTER: &str = "info";
+// The target of the timings of the system runs, they are logged at trace level
+pub const SPAN_TARGET: &str = "span";
+
+// Writes to stderr, prefixed with the seconds since the start, the level and the module.
+// The filter looks like `warn,doem::obj_loader=debug,span=trace`: a default level followed by
+// levels for the modules which start with the given path, the longest matching path wins.
+pub struct Logger {
+    default: LevelFilter,
+    modules: Vec<(String, LevelFilter)>,
+    start: Instant,
+}
+
+impl Logger {
+    pub fn parse(filter: &str) -> Result<Logger, String> {
+        let mut default = LevelFilter::Info;
+        let mut modules: Vec<(String, LevelFilter)> = Vec::new();
+        for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let mut parts = directive.splitn(2, '=');
+            let (module, level) = match (parts.next(), parts.next()) {
+                (Some(module), Some(level)) => (Some(module.trim()), level.trim()),
+                (Some(level), None) => (None, level),
+                _ => unreachable!(),
+            };
+            let level = LevelFilter::from_str(level)
+                .map_err(|_| format!("`{}` is not a log level", level))?;
+            match module {
+                Some(module) => modules.push((module.to_owned(), level)),
+                None => default = level,
+            }
+        }
+        // The longest paths are the most specific, so they are tried first
+        modules.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        Ok(Logger {
+            default,
+            modules,
+            start: Instant::now(),
+        })
+    }
+    fn level(&self, target: &str) -> LevelFilter {
+        self.modules
+            .iter()
+            .find(|(module, _)| target.starts_with(module.as_str()))
+            .map_or(self.default, |(_, level)| *level)
+    }
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let max_level = self
+            .modules
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, std::cmp::max);
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level(metadata.target())
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        eprintln!(
+            "{:>9.3} {:<5} {}: {}",
+            self.start.elapsed().as_secs_f32(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+    fn flush(&self) {}
+}
+
+// Logs how long it lived when it is dropped
+pub struct Span {
+    name: &'static str,
+    start: Instant,
+}
+
+impl Span {
+    pub fn enter(name: &'static str) -> Span {
+        Span {
+            name,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        trace!(
+            target: SPAN_TARGET,
+            "{} took {} us",
+            self.name,
+            self.start.elapsed().as_micros()
+        );
+    }
+}
@@ -1,58 +1,382 @@
+mod asset_error;
+mod bitmap_font;
 mod consts;
 mod data;
 mod ecs;
+mod file_watcher;
+mod frustum;
 mod gl_common;
+mod logger;
 mod obj_loader;
+mod random;
+mod replay;
+mod screenshot;
+mod settings;
+mod skybox;
 mod tess_manager;
+mod weapons;
 
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate log;
 
+use crate::ecs::components::ai_pilot::{AiPilot, Steering};
+use crate::ecs::components::armor::{Armor, Resistances};
 use crate::ecs::components::camera::Camera;
 use crate::ecs::components::collider::Collider;
+use crate::ecs::components::fire_intent::FireIntent;
 use crate::ecs::components::gun::Gun;
 use crate::ecs::components::health::Health;
+use crate::ecs::components::level_of_detail::{LevelOfDetail, LodLevel};
+use crate::ecs::components::lives::Lives;
+use crate::ecs::components::particle_emitter::ParticleEmitter;
 use crate::ecs::components::physics::Physics;
 use crate::ecs::components::pulsate::Pulsate;
 use crate::ecs::components::shape::Shape;
+use crate::ecs::components::shield::Shield;
 use crate::ecs::components::thruster::Thruster;
 use crate::ecs::components::transform::Transform;
 use crate::ecs::components::transformable::Transformable;
 use crate::ecs::dispatcher::DoemDispatcher;
+use crate::ecs::resources::background::{Background, Skybox, Starfield};
+use crate::ecs::resources::console::Console;
+use crate::ecs::resources::doem_events::DoemEvents;
+use crate::ecs::resources::frame_check::FrameCheck;
+use crate::ecs::resources::game_state::RespawnPoint;
+use crate::ecs::resources::hud::HudLayout;
+use crate::ecs::resources::particles::Particles;
+use crate::ecs::resources::preload::Preload;
+use crate::ecs::resources::profiler::Profiler;
 use crate::ecs::world::DoemWorld;
-use clap::App;
+use crate::logger::{Logger, Span};
+use crate::obj_loader::ObjLoader;
+use crate::replay::{Replay, ReplayMode};
+use crate::screenshot::Frames;
+use crate::settings::{Settings, SettingsError};
+use crate::weapons::{Weapon, WeaponError, Weapons};
+use clap::{value_t, App, Arg, ArgMatches, SubCommand};
 use doem_math::{Matrix4, Vector3, PI};
+use glfw::SwapInterval;
 use luminance_glfw::{GlfwSurface, Surface, WindowDim, WindowOpt};
 use specs::prelude::*;
 use specs::WorldExt;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 fn main() {
-    App::new("Doem")
+    let mut app = App::new("Doem")
         .version("1.0")
         .author("Bram-Boris Meerlo and Peter-Jan Gootzen")
-        .about("Made using our own linear algebra crate doem-math.");
+        .about("Made using our own linear algebra crate doem-math.")
+        .arg(
+            Arg::with_name("dev")
+                .long("dev")
+                .help("Reloads the shaders and models when they change on disk"),
+        )
+        .arg(
+            Arg::with_name("stress-bullets")
+                .long("stress-bullets")
+                .takes_value(true)
+                .value_name("COUNT")
+                .help("Spawns COUNT bullets and prints the frame rate every second"),
+        )
+        .arg(
+            Arg::with_name("render-frames")
+                .long("render-frames")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Writes every rendered frame as a png into DIR"),
+        )
+        .arg(
+            Arg::with_name("compare-frames")
+                .long("compare-frames")
+                .takes_value(true)
+                .value_name("DIR")
+                .conflicts_with("render-frames")
+                .help("Compares every rendered frame with the ones --render-frames wrote into DIR"),
+        )
+        .arg(
+            Arg::with_name("settings")
+                .long("settings")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Reads the window and graphics settings from FILE, defaults to settings.cfg"),
+        )
+        .arg(
+            Arg::with_name("weapons")
+                .long("weapons")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Reads the weapon definitions from FILE, defaults to weapons.cfg"),
+        )
+        .arg(
+            Arg::with_name("record")
+                .long("record")
+                .takes_value(true)
+                .value_name("FILE")
+                .conflicts_with("replay")
+                .help("Records the input of every tick into FILE, so the run can be replayed"),
+        )
+        .arg(
+            Arg::with_name("replay")
+                .long("replay")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Plays a recorded run again and checks that every tick ends in the same state"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("Writes the timings of every system run as a Chrome trace into FILE on exit"),
+        )
+        .arg(
+            Arg::with_name("log")
+                .long("log")
+                .takes_value(true)
+                .value_name("FILTER")
+                .help("Sets the log levels like `warn,doem::obj_loader=debug,span=trace`, overrides DOEM_LOG"),
+        )
+        .subcommand(
+            SubCommand::with_name("bake")
+                .about("Converts the obj files in the models directory into baked meshes"),
+        );
+    // Every setting can also be given on the command line, which wins over the settings file
+    for (key, help) in settings::KEYS.iter() {
+        app = app.arg(
+            Arg::with_name(key)
+                .long(key)
+                .takes_value(true)
+                .value_name("VALUE")
+                .help(help),
+        );
+    }
+    let matches = app.get_matches();
+
+    let filter = match matches.value_of("log") {
+        Some(filter) => filter.to_owned(),
+        None => env::var(logger::LOG_ENV).unwrap_or_else(|_| logger::DEFAULT_FILTER.to_owned()),
+    };
+    match Logger::parse(&filter) {
+        Ok(logger) => logger.init().expect("The logger could only be set once"),
+        Err(e) => {
+            eprintln!("Invalid log filter: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if matches.subcommand_matches("bake").is_some() {
+        bake(consts::MODELS_DIR);
+        return;
+    }
+
+    let stress_bullets = if matches.is_present("stress-bullets") {
+        value_t!(matches, "stress-bullets", usize).unwrap_or_else(|e| e.exit())
+    } else {
+        0
+    };
+
+    let frames = match (
+        matches.value_of("render-frames"),
+        matches.value_of("compare-frames"),
+    ) {
+        (Some(dir), _) => Some(Frames::Render(PathBuf::from(dir))),
+        (None, Some(dir)) => Some(Frames::Compare(PathBuf::from(dir))),
+        (None, None) => None,
+    };
+    let trace_path = matches.value_of("trace").map(PathBuf::from);
+
+    // A replay brings its own scene
+    let mut replay_mode = if let Some(path) = matches.value_of("replay") {
+        match Replay::load(path) {
+            Ok(replay) => ReplayMode::Play(replay),
+            Err(e) => {
+                error!("Invalid replay: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(path) = matches.value_of("record") {
+        ReplayMode::Record(PathBuf::from(path), Replay::new(stress_bullets))
+    } else {
+        ReplayMode::Off
+    };
+    let stress_bullets = match &replay_mode {
+        ReplayMode::Play(replay) => replay.stress_bullets,
+        _ => stress_bullets,
+    };
+
+    let settings = match load_settings(&matches) {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("Invalid settings: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let weapons = match load_weapons(&matches, &mut replay_mode) {
+        Ok(weapons) => weapons,
+        Err(e) => {
+            error!("Invalid weapons: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    start(
+        settings,
+        weapons,
+        matches.is_present("dev"),
+        stress_bullets,
+        frames,
+        trace_path,
+        replay_mode,
+    );
+}
+
+fn load_settings(matches: &ArgMatches) -> Result<Settings, SettingsError> {
+    let mut settings = match matches.value_of("settings") {
+        Some(path) => Settings::load(path)?,
+        // The default settings file is optional
+        None if Path::new(settings::SETTINGS_PATH).exists() => {
+            Settings::load(settings::SETTINGS_PATH)?
+        }
+        None => Settings::default(),
+    };
+    for (key, _) in settings::KEYS.iter() {
+        if let Some(value) = matches.value_of(key) {
+            settings.set(key, value)?;
+        }
+    }
+    settings.validate()?;
+    Ok(settings)
+}
+
+// The weapons of the player and the enemies have to be there. A replay brings its own weapons
+// and a recording keeps them.
+fn load_weapons(
+    matches: &ArgMatches,
+    replay_mode: &mut ReplayMode,
+) -> Result<Weapons, WeaponError> {
+    let source = match replay_mode {
+        ReplayMode::Play(replay) => replay.weapons.clone(),
+        _ => Weapons::read(matches.value_of("weapons").unwrap_or(weapons::WEAPONS_PATH))?,
+    };
+    if let ReplayMode::Record(_, replay) = replay_mode {
+        replay.weapons = source.clone();
+    }
+    let weapons = Weapons::parse(&source)?;
+    weapons.get(consts::PLAYER_WEAPON)?;
+    weapons.get(consts::ENEMY_WEAPON)?;
+    Ok(weapons)
+}
 
-    start();
+fn bake<P>(models_dir: P)
+where
+    P: AsRef<Path>,
+{
+    let entries = fs::read_dir(models_dir).expect("The models directory could not be read");
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.extension().map_or(true, |e| e != "obj") {
+            continue;
+        }
+        match ObjLoader::bake(&path) {
+            Ok(baked_path) => info!("baked {} into {}", path.display(), baked_path.display()),
+            Err(e) => error!("could not bake {}: {}", path.display(), e),
+        }
+    }
 }
 
-fn start() {
-    let surface = GlfwSurface::new(WindowDim::Windowed(1600, 900), "Doem", WindowOpt::default())
+fn start(
+    settings: Settings,
+    weapons: Weapons,
+    hot_reload: bool,
+    stress_bullets: usize,
+    frame_capture: Option<Frames>,
+    trace_path: Option<PathBuf>,
+    mut replay_mode: ReplayMode,
+) {
+    let dim = if settings.fullscreen {
+        WindowDim::FullscreenRestricted(settings.width, settings.height)
+    } else {
+        WindowDim::Windowed(settings.width, settings.height)
+    };
+    let samples = if settings.msaa > 0 {
+        Some(settings.msaa)
+    } else {
+        None
+    };
+    let mut surface = GlfwSurface::new(dim, "Doem", WindowOpt::default().set_num_samples(samples))
         .expect("GLFW surface creation");
+    // luminance-glfw always turns vsync on, the swap interval applies to the current context
+    surface.window.glfw.set_swap_interval(if settings.vsync {
+        SwapInterval::Sync(1)
+    } else {
+        SwapInterval::None
+    });
 
     let should_quit = Arc::new(Mutex::new(false));
     let mut world = DoemWorld::new();
+    let mut profiler = Profiler::default();
+    if trace_path.is_some() {
+        profiler.start_trace();
+    }
+    let recorder = profiler.recorder();
+    world.insert(profiler);
+    if let Some(seed) = replay_mode.seed() {
+        world.insert(Particles::new(seed));
+    }
+    // Projectiles only appear once something shoots, so warm the cache for them up front
+    let mut projectiles: Vec<String> = weapons.0.iter().map(|w| w.projectile.clone()).collect();
+    projectiles.sort();
+    projectiles.dedup();
+    world.insert(Preload(projectiles));
+    let player_weapon = weapons
+        .get(consts::PLAYER_WEAPON)
+        .expect("The player weapon is checked when loading")
+        .clone();
+    let enemy_weapon = weapons
+        .get(consts::ENEMY_WEAPON)
+        .expect("The enemy weapon is checked when loading")
+        .clone();
+    world.insert(weapons);
+    world.insert(HudLayout {
+        visible: settings.hud_scale > 0.0,
+        scale: settings.hud_scale,
+        ..HudLayout::default()
+    });
+    world.insert(Background {
+        skybox: Some(Skybox::Gradient {
+            zenith: [0.0, 0.0, 0.02],
+            horizon: [0.04, 0.02, 0.08],
+            nadir: [0.0, 0.0, 0.0],
+        }),
+        starfield: Some(Starfield {
+            layers: 3,
+            stars_per_layer: 2000,
+            layer_size: 3000.0,
+            brightness: 1.0,
+        }),
+    });
 
+    let respawn_point = RespawnPoint {
+        position: Vector3::from([[0.0], [200.0], [1000.0]]),
+        orientation: Matrix4::get_rotation_y(PI * 1.5),
+    };
     world
         .create_entity()
         .with(Shape::Unit {
             obj_path: consts::STARSHIP_OBJ_PATH.to_owned(),
         })
         .with(Transform {
-            position: Vector3::from([[0.0], [200.0], [1000.0]]),
+            position: respawn_point.position.clone(),
             scale: Vector3::from([[10.0], [10.0], [10.0]]),
-            orientation: Matrix4::get_rotation_y(PI * 1.5),
+            orientation: respawn_point.orientation.clone(),
         })
         .with(Physics {
             velocity: Vector3::from([[0.00], [0.0], [0.0]]),
@@ -66,12 +390,27 @@ fn start() {
             offset: Vector3::from([[20.0], [10.0], [0.0]]),
             orientation: Matrix4::identity(),
         })
-        .with(Gun {
-            damage: consts::STARSHIP_BULLET_DAMAGE,
-            velocity: consts::STARSHIP_BULLET_VELOCITY.clone(),
-            despawn_bullet_on_impact: true,
+        .with(Collider {
+            half_size: Vector3::from([[1.0], [1.0], [1.0]]),
+        })
+        .with(Health {
+            health: consts::PLAYER_HEALTH,
+        })
+        .with(Lives {
+            remaining: consts::PLAYER_LIVES,
+            health: consts::PLAYER_HEALTH,
+        })
+        .with(Shield::new(100.0, 0.3, 120.0))
+        .with(Armor {
+            resistances: Resistances::default(),
+            reduction: 1.0,
+            invulnerability: 20.0,
         })
+        .with(Gun::new(player_weapon.clone()))
+        .with(FireIntent::default())
+        .with(ParticleEmitter::thrust())
         .build();
+    world.insert(respawn_point);
 
     world
         .create_entity()
@@ -105,6 +444,17 @@ fn start() {
             half_size: Vector3::from([[1.0], [1.0], [1.0]]),
         })
         .with(Health { health: 100.0 })
+        .with(LevelOfDetail::new(
+            consts::NONDESCRIPTCIRCLE_LODS
+                .iter()
+                .map(|(cells, distance)| {
+                    let obj_path =
+                        ObjLoader::detail_path(consts::NONDESCRIPTCIRCLE_OBJ_PATH, *cells);
+                    LodLevel::new(&obj_path, *distance)
+                })
+                .collect(),
+            consts::LOD_HYSTERESIS,
+        ))
         .build();
 
     world
@@ -141,14 +491,197 @@ fn start() {
         })
         .build();
 
-    let mut dispatcher = DoemDispatcher::new(surface, should_quit.clone());
+    spawn_enemies(&mut world, &enemy_weapon);
+    spawn_stress_bullets(&mut world, stress_bullets, &player_weapon);
+
+    let mut dispatcher = DoemDispatcher::new(
+        surface,
+        &settings,
+        should_quit.clone(),
+        hot_reload,
+        replay_mode.is_on(),
+        frame_capture.clone(),
+        recorder,
+    );
     dispatcher.setup(&mut world);
+    let mut frames = 0;
+    let mut frames_since = Instant::now();
+    let mut tick = 0;
+    let mut diverged = false;
     'game_loop: loop {
-        dispatcher.dispatch(&world);
-        world.maintain();
+        // The input a replay recorded replaces the live input
+        if let ReplayMode::Play(replay) = &replay_mode {
+            match replay.ticks.get(tick) {
+                Some(recorded) => {
+                    world.write_resource::<DoemEvents>().0 = recorded.events.clone();
+                    world.write_resource::<Console>().commands = recorded.commands.clone();
+                }
+                None => {
+                    if !diverged {
+                        info!(
+                            "The replay reached the recorded state in all {} ticks",
+                            tick
+                        );
+                    }
+                    break 'game_loop;
+                }
+            }
+        }
+        let events = world.read_resource::<DoemEvents>().0.clone();
+        let commands = world.read_resource::<Console>().commands.clone();
+        {
+            let _span = Span::enter("tick");
+            dispatcher.dispatch(&world);
+            world.maintain();
+        }
+        match &mut replay_mode {
+            ReplayMode::Off => (),
+            ReplayMode::Record(_, replay) => {
+                replay.record(&events, &commands, replay::state_hash(&world));
+            }
+            ReplayMode::Play(replay) => {
+                if !diverged && replay::state_hash(&world) != replay.ticks[tick].hash {
+                    error!("The replay diverged from the recording at tick {}", tick);
+                    diverged = true;
+                }
+            }
+        }
+        tick += 1;
+        world.write_resource::<Profiler>().end_frame();
         if *(*should_quit).lock().unwrap() {
             break 'game_loop;
         }
+        if stress_bullets > 0 {
+            frames += 1;
+            if frames_since.elapsed() >= Duration::from_secs(1) {
+                info!("{} fps with {} bullets", frames, stress_bullets);
+                frames = 0;
+                frames_since = Instant::now();
+            }
+        }
     }
     dispatcher.dispose(&mut world);
+
+    if let ReplayMode::Record(path, replay) = &replay_mode {
+        match replay.save(path) {
+            Ok(()) => info!(
+                "Recorded {} ticks into {}",
+                replay.ticks.len(),
+                path.display()
+            ),
+            Err(e) => error!("Could not save the recording: {}", e),
+        }
+    }
+
+    let profiler = world.read_resource::<Profiler>();
+    for name in profiler.names() {
+        let stats = profiler.stats(name);
+        info!(
+            "{}: min {:.2} ms, avg {:.2} ms, max {:.2} ms, p99 {:.2} ms",
+            name, stats.min, stats.avg, stats.max, stats.p99
+        );
+    }
+    if let Some(path) = trace_path {
+        match profiler.write_trace(&path) {
+            Ok(()) => info!("Wrote the trace to {}", path.display()),
+            Err(e) => error!("Could not write the trace to {}: {}", path.display(), e),
+        }
+    }
+
+    if let Some(Frames::Compare(dir)) = &frame_capture {
+        let frame_check = world.read_resource::<FrameCheck>();
+        if frame_check.differing.is_empty() {
+            info!(
+                "All {} frames match the golden frames in {}",
+                frame_check.compared,
+                dir.display()
+            );
+        } else {
+            error!(
+                "{} of {} frames differ from the golden frames in {}: {:?}",
+                frame_check.differing.len(),
+                frame_check.compared,
+                dir.display(),
+                frame_check.differing
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+// Hostile ships around the title, one hunting the player and one circling it
+fn spawn_enemies(world: &mut World, weapon: &Weapon) {
+    let enemies = vec![
+        ([-800.0, 300.0, -600.0], AiPilot::new(Steering::Pursue)),
+        (
+            [800.0, 400.0, -600.0],
+            AiPilot::new(Steering::Orbit { radius: 400.0 }),
+        ),
+    ];
+    for (position, pilot) in enemies {
+        world
+            .create_entity()
+            .with(Shape::Unit {
+                obj_path: consts::STARSHIP_OBJ_PATH.to_owned(),
+            })
+            .with(Transform {
+                position: Vector3::from([[position[0]], [position[1]], [position[2]]]),
+                scale: Vector3::from([[10.0], [10.0], [10.0]]),
+                orientation: Matrix4::identity(),
+            })
+            .with(Physics {
+                velocity: Vector3::from([[0.0], [0.0], [0.0]]),
+            })
+            .with(Thruster {
+                power: Vector3::from([[0.1], [0.0], [0.0]]),
+            })
+            .with(Collider {
+                half_size: Vector3::from([[1.0], [1.0], [1.0]]),
+            })
+            .with(Health { health: 100.0 })
+            .with(Shield::new(50.0, 0.2, 120.0))
+            .with(Armor {
+                resistances: Resistances {
+                    kinetic: 0.25,
+                    ..Resistances::default()
+                },
+                reduction: 1.0,
+                invulnerability: 10.0,
+            })
+            .with(Gun::new(weapon.clone()))
+            .with(FireIntent::default())
+            .with(ParticleEmitter::thrust())
+            .with(pilot)
+            .build();
+    }
+}
+
+// A cube of motionless projectiles of the weapon in front of the starship, they all share one
+// tesselation
+fn spawn_stress_bullets(world: &mut World, count: usize, weapon: &Weapon) {
+    let side = (count as f32).cbrt().ceil() as usize;
+    for i in 0..count {
+        let x = (i % side) as f32;
+        let y = ((i / side) % side) as f32;
+        let z = (i / (side * side)) as f32;
+        world
+            .create_entity()
+            .with(Shape::Unit {
+                obj_path: weapon.projectile.clone(),
+            })
+            .with(Transform {
+                position: Vector3::from([
+                    [-200.0 - x * 20.0],
+                    [100.0 + y * 20.0],
+                    [800.0 - z * 20.0],
+                ]),
+                scale: Vector3::from([
+                    [weapon.projectile_scale],
+                    [weapon.projectile_scale],
+                    [weapon.projectile_scale],
+                ]),
+                orientation: Matrix4::identity(),
+            })
+            .build();
+    }
 }
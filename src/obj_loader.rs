@@ -1,16 +1,18 @@
+use crate::asset_error::AssetError;
 use crate::data::AABB;
-use doem_math::Vector3;
+use doem_math::{Matrix4, Vector3};
 use luminance::context::GraphicsContext;
 use luminance::tess::{Mode, Tess, TessBuilder, TessError};
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
-use std::path::Path;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use wavefront_obj::obj;
 
-use crate::gl_common::{Vertex, VertexColor, VertexPosition};
+use crate::gl_common::{Instance, Vertex, VertexColor, VertexPosition};
 
 pub struct ObjLoader {
+    mode: Mode,
     vertices: Vec<Vertex>,
     indices: Vec<VertexIndex>,
     pub middle_point: Vector3,
@@ -21,28 +23,293 @@ pub struct ObjLoader {
 
 type VertexIndex = u32;
 
+// Layout of a baked mesh, all values little endian:
+// magic, version, middle point (3 x f32), half sizes (3 x f32),
+// vertex count (u32), index count (u32), vertices (position and color, 6 x f32), indices (u32)
+const BAKED_MAGIC: &[u8; 8] = b"DOEMMESH";
+const BAKED_VERSION: u32 = 1;
+const BAKED_HEADER_SIZE: u64 = 8 + 4 + 6 * 4 + 4 + 4;
+const BAKED_VERTEX_SIZE: u64 = 6 * 4;
+const BAKED_INDEX_SIZE: u64 = 4;
+pub const BAKED_EXTENSION: &str = "dmesh";
+// models/donut.obj#16 is models/donut.obj simplified to 16 cells along its longest side
+const DETAIL_SEPARATOR: char = '#';
+
 impl ObjLoader {
-    pub fn build_tess<C>(self, ctx: &mut C) -> Result<Tess, TessError>
+    // The instance buffer can hold the transformations of instance_capacity instances,
+    // it has to be rebuilt to draw more instances in one call
+    pub fn build_tess<C>(&self, ctx: &mut C, instance_capacity: usize) -> Result<Tess, TessError>
     where
         C: GraphicsContext,
     {
+        let instances = vec![Instance::from_transform(&Matrix4::identity()); instance_capacity];
         TessBuilder::new(ctx)
-            .set_mode(Mode::Triangle)
-            .add_vertices(self.vertices)
-            .set_indices(self.indices)
+            .set_mode(self.mode)
+            .add_vertices(&self.vertices)
+            .add_instances(instances)
+            .set_indices(&self.indices)
             .build()
     }
-    pub fn load<P>(path: P) -> Result<Self, String>
+    pub fn detail_path(obj_path: &str, cells: u32) -> String {
+        format!("{}{}{}", obj_path, DETAIL_SEPARATOR, cells)
+    }
+    // The obj file a detail path is made from
+    pub fn detail_source(path: &str) -> &str {
+        path.rsplitn(2, DETAIL_SEPARATOR).last().unwrap_or(path)
+    }
+    // Loads an obj file like load_cached, or a simplified version of it for a detail path
+    pub fn load_detail(path: &str) -> Result<Self, AssetError> {
+        let source = Self::detail_source(path);
+        if source == path {
+            return Self::load_cached(path);
+        }
+        let cells = path[source.len() + 1..]
+            .parse()
+            .map_err(|_| AssetError::InvalidDetail(path.to_owned()))?;
+        let simplified = Self::load_cached(source)?.simplify(cells);
+        if simplified.indices.is_empty() {
+            return Err(AssetError::EmptyMesh(path.to_owned()));
+        }
+        Ok(simplified)
+    }
+    // Vertex clustering: the vertices in one cell of a grid over the bounding box are merged
+    // into their average and the triangles which collapse are dropped
+    pub fn simplify(&self, cells: u32) -> Self {
+        let longest = self.x_half_size.max(self.y_half_size).max(self.z_half_size);
+        let cell_size = longest * 2.0 / cells.max(1) as f32;
+        let cell = |value: f32, middle: f32| {
+            if cell_size > 0.0 {
+                ((value - middle) / cell_size).floor() as i32
+            } else {
+                0
+            }
+        };
+
+        let mut clusters: HashMap<[i32; 3], VertexIndex> = HashMap::new();
+        let mut sums: Vec<([f32; 3], f32)> = Vec::new();
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut remap: Vec<VertexIndex> = Vec::with_capacity(self.vertices.len());
+        for v in &self.vertices {
+            let pos = *v.pos;
+            let key = [
+                cell(pos[0], self.middle_point[0][0]),
+                cell(pos[1], self.middle_point[1][0]),
+                cell(pos[2], self.middle_point[2][0]),
+            ];
+            let index = match clusters.get(&key) {
+                Some(index) => *index,
+                None => {
+                    let index = vertices.len() as VertexIndex;
+                    clusters.insert(key, index);
+                    vertices.push(*v);
+                    sums.push(([0.0; 3], 0.0));
+                    index
+                }
+            };
+            let (sum, count) = &mut sums[index as usize];
+            for (total, value) in sum.iter_mut().zip(pos.iter()) {
+                *total += value;
+            }
+            *count += 1.0;
+            remap.push(index);
+        }
+        for (v, (sum, count)) in vertices.iter_mut().zip(sums.iter()) {
+            v.pos = VertexPosition::new([sum[0] / count, sum[1] / count, sum[2] / count]);
+        }
+
+        let mut indices: Vec<VertexIndex> = Vec::new();
+        for triangle in self.indices.chunks(3) {
+            if triangle.len() < 3 {
+                break;
+            }
+            let (a, b, c) = (
+                remap[triangle[0] as usize],
+                remap[triangle[1] as usize],
+                remap[triangle[2] as usize],
+            );
+            if a != b && b != c && a != c {
+                indices.extend_from_slice(&[a, b, c]);
+            }
+        }
+
+        Self {
+            mode: self.mode,
+            vertices,
+            indices,
+            middle_point: self.middle_point.clone(),
+            x_half_size: self.x_half_size,
+            y_half_size: self.y_half_size,
+            z_half_size: self.z_half_size,
+        }
+    }
+    // Loads the baked version of an obj file when it is up to date, otherwise parses the obj file
+    pub fn load_cached<P>(path: P) -> Result<Self, AssetError>
+    where
+        P: AsRef<Path>,
+    {
+        let baked_path = Self::baked_path(&path);
+        if Self::is_newer(&baked_path, &path) {
+            match Self::load_baked(&baked_path) {
+                Ok(loaded) => return Ok(loaded),
+                Err(e) => warn!(
+                    "Could not load baked mesh {}, parsing the obj file instead: {}",
+                    baked_path.display(),
+                    e
+                ),
+            }
+        }
+        Self::load(path)
+    }
+    pub fn baked_path<P>(path: P) -> PathBuf
+    where
+        P: AsRef<Path>,
+    {
+        path.as_ref().with_extension(BAKED_EXTENSION)
+    }
+    fn is_newer<P, Q>(path: P, than: Q) -> bool
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+        match (modified(path.as_ref()), modified(than.as_ref())) {
+            (Some(baked), Some(source)) => baked >= source,
+            (Some(_), None) => true,
+            _ => false,
+        }
+    }
+    // Parses an obj file and writes it next to it in the baked format
+    pub fn bake<P>(path: P) -> Result<PathBuf, AssetError>
     where
         P: AsRef<Path>,
     {
+        let baked_path = Self::baked_path(&path);
+        Self::load(path)?.save_baked(&baked_path)?;
+        Ok(baked_path)
+    }
+    pub fn save_baked<P>(&self, path: P) -> Result<(), AssetError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(BAKED_MAGIC)?;
+        out.write_all(&BAKED_VERSION.to_le_bytes())?;
+        for value in &[
+            self.middle_point[0][0],
+            self.middle_point[1][0],
+            self.middle_point[2][0],
+            self.x_half_size,
+            self.y_half_size,
+            self.z_half_size,
+        ] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        out.write_all(&(self.vertices.len() as u32).to_le_bytes())?;
+        out.write_all(&(self.indices.len() as u32).to_le_bytes())?;
+        for v in &self.vertices {
+            for value in (*v.pos).iter().chain((*v.color).iter()) {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+        for i in &self.indices {
+            out.write_all(&i.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+    pub fn load_baked<P>(path: P) -> Result<Self, AssetError>
+    where
+        P: AsRef<Path>,
+    {
+        let path_name = path.as_ref().display().to_string();
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut input = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != BAKED_MAGIC {
+            return Err(AssetError::InvalidBakedMesh(path_name));
+        }
+        if Self::read_u32(&mut input)? != BAKED_VERSION {
+            return Err(AssetError::InvalidBakedMesh(path_name));
+        }
+
+        let middle_point = Vector3::from([
+            [Self::read_f32(&mut input)?],
+            [Self::read_f32(&mut input)?],
+            [Self::read_f32(&mut input)?],
+        ]);
+        let x_half_size = Self::read_f32(&mut input)?;
+        let y_half_size = Self::read_f32(&mut input)?;
+        let z_half_size = Self::read_f32(&mut input)?;
+
+        let vertex_count = Self::read_u32(&mut input)? as usize;
+        let index_count = Self::read_u32(&mut input)? as usize;
+        if vertex_count == 0 || index_count == 0 {
+            return Err(AssetError::EmptyMesh(path_name));
+        }
+        // The counts come from the file, a truncated or corrupt one must not make us allocate
+        // more than it holds
+        let body_size = (vertex_count as u64 * BAKED_VERTEX_SIZE)
+            .checked_add(index_count as u64 * BAKED_INDEX_SIZE)
+            .and_then(|size| size.checked_add(BAKED_HEADER_SIZE));
+        if body_size != Some(file_size) {
+            return Err(AssetError::InvalidBakedMesh(path_name));
+        }
+
+        let mut vertices: Vec<Vertex> = Vec::with_capacity(vertex_count);
+        for _ in 0..vertex_count {
+            let mut v = [0.0f32; 6];
+            for value in v.iter_mut() {
+                *value = Self::read_f32(&mut input)?;
+            }
+            vertices.push(Vertex {
+                pos: VertexPosition::new([v[0], v[1], v[2]]),
+                color: VertexColor::new([v[3], v[4], v[5]]),
+            });
+        }
+        let mut indices: Vec<VertexIndex> = Vec::with_capacity(index_count);
+        for _ in 0..index_count {
+            let index = Self::read_u32(&mut input)?;
+            if index as usize >= vertex_count {
+                return Err(AssetError::InvalidBakedMesh(path_name));
+            }
+            indices.push(index);
+        }
+
+        Ok(Self {
+            mode: Mode::Triangle,
+            vertices,
+            indices,
+            middle_point,
+            x_half_size,
+            y_half_size,
+            z_half_size,
+        })
+    }
+    fn read_u32<R: Read>(input: &mut R) -> Result<u32, AssetError> {
+        let mut bytes = [0u8; 4];
+        input.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+    fn read_f32<R: Read>(input: &mut R) -> Result<f32, AssetError> {
+        let mut bytes = [0u8; 4];
+        input.read_exact(&mut bytes)?;
+        Ok(f32::from_le_bytes(bytes))
+    }
+    pub fn load<P>(path: P) -> Result<Self, AssetError>
+    where
+        P: AsRef<Path>,
+    {
+        let path_name = path.as_ref().display().to_string();
         let file_content = {
-            let mut file = File::open(path).map_err(|e| format!("cannot open file: {}", e))?;
+            let mut file = File::open(path)?;
             let mut content = String::new();
-            file.read_to_string(&mut content).unwrap();
+            file.read_to_string(&mut content)?;
             content
         };
-        let obj_set = obj::parse(file_content).map_err(|e| format!("cannot parse: {:?}", e))?;
+        let obj_set = obj::parse(file_content)?;
         let objects = obj_set.objects;
 
         let mut vertices: Vec<Vertex> = Vec::new();
@@ -56,11 +323,11 @@ impl ObjLoader {
         let mut max_z: Option<f32> = None;
 
         for object in objects.into_iter() {
-            println!("loading object {}", object.name);
-            println!("{} vertices in object", object.vertices.len());
-            println!("{} geometries in object", object.geometry.len());
+            debug!("loading object {}", object.name);
+            debug!("{} vertices in object", object.vertices.len());
+            debug!("{} geometries in object", object.geometry.len());
             for geometry in object.geometry {
-                println!("{} shapes", geometry.shapes.len());
+                debug!("{} shapes", geometry.shapes.len());
 
                 // build up vertices; for this to work, we remove duplicated vertices by putting them in a
                 // map associating the vertex with its ID
@@ -92,18 +359,19 @@ impl ObjLoader {
                             }
                         }
                     } else {
-                        println!("unsupported non-triangle shape");
+                        warn!("unsupported non-triangle shape in {}", path_name);
                     }
                 }
             }
         }
 
-        let min_x = min_x.unwrap();
-        let min_y = min_y.unwrap();
-        let min_z = min_z.unwrap();
-        let max_x = max_x.unwrap();
-        let max_y = max_y.unwrap();
-        let max_z = max_z.unwrap();
+        let (min_x, min_y, min_z, max_x, max_y, max_z) =
+            match (min_x, min_y, min_z, max_x, max_y, max_z) {
+                (Some(min_x), Some(min_y), Some(min_z), Some(max_x), Some(max_y), Some(max_z)) => {
+                    (min_x, min_y, min_z, max_x, max_y, max_z)
+                }
+                _ => return Err(AssetError::EmptyMesh(path_name)),
+            };
 
         let x_half_size = (max_x - min_x) / 2.0;
         let y_half_size = (max_y - min_y) / 2.0;
@@ -122,10 +390,11 @@ impl ObjLoader {
         }
         middle_point = Vector3::origin();
 
-        println!("{} total amount of vertices for obj file", vertices.len());
-        println!("{} total amount of indices for obj file", indices.len());
+        debug!("{} total amount of vertices for obj file", vertices.len());
+        debug!("{} total amount of indices for obj file", indices.len());
 
         Ok(Self {
+            mode: Mode::Triangle,
             vertices,
             indices,
             middle_point,
@@ -135,6 +404,39 @@ impl ObjLoader {
         })
     }
 
+    // The "missing model" mesh: a magenta octahedron which is drawn in place of a model that
+    // could not be loaded
+    pub fn generate_placeholder() -> Self {
+        let color = VertexColor::new([1.0, 0.0, 1.0]);
+        let vertices: Vec<Vertex> = [
+            [1.0, 0.0, 0.0],
+            [-1.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0],
+            [0.0, -1.0, 0.0],
+            [0.0, 0.0, 1.0],
+            [0.0, 0.0, -1.0],
+        ]
+        .iter()
+        .map(|p| Vertex {
+            pos: VertexPosition::new(*p),
+            color,
+        })
+        .collect();
+        let indices: Vec<VertexIndex> = vec![
+            0, 2, 4, 4, 2, 1, 1, 2, 5, 5, 2, 0, 0, 4, 3, 4, 1, 3, 1, 5, 3, 5, 0, 3,
+        ];
+
+        Self {
+            mode: Mode::Triangle,
+            vertices,
+            indices,
+            middle_point: Vector3::origin(),
+            x_half_size: 1.0,
+            y_half_size: 1.0,
+            z_half_size: 1.0,
+        }
+    }
+
     fn parse_min_value(old: Option<f32>, new: f32) -> Option<f32> {
         match old {
             None => Some(new),
@@ -159,10 +461,7 @@ impl ObjLoader {
             }
         }
     }
-    pub fn generate_aabb<C>(aabb: &AABB, ctx: &mut C) -> Result<Tess, TessError>
-    where
-        C: GraphicsContext,
-    {
+    pub fn generate_aabb(aabb: &AABB) -> Self {
         let mut aabb_vertices: Vec<Vertex> = Vec::new();
 
         let color = VertexColor::new([0.0, 1.0, 0.0]);
@@ -226,10 +525,149 @@ impl ObjLoader {
             0, 1, 0, 3, 0, 2, 1, 0, 1, 6, 1, 4, 6, 3, 6, 7, 3, 5, 2, 4, 2, 5, 7, 4, 7, 5, 8, 9, 8,
             10, 8, 11,
         ];
-        TessBuilder::new(ctx)
-            .set_mode(Mode::Line)
-            .add_vertices(aabb_vertices)
-            .set_indices(aabb_indices)
-            .build()
+        Self {
+            mode: Mode::Line,
+            vertices: aabb_vertices,
+            indices: aabb_indices,
+            middle_point: aabb.middle_point.clone(),
+            x_half_size: aabb.half_size[0][0],
+            y_half_size: aabb.half_size[1][0],
+            z_half_size: aabb.half_size[2][0],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom};
+
+    fn triangle() -> ObjLoader {
+        let vertex = |x: f32, y: f32| Vertex {
+            pos: VertexPosition::new([x, y, 0.0]),
+            color: VertexColor::new([1.0, 0.5, 0.0]),
+        };
+        ObjLoader {
+            mode: Mode::Triangle,
+            vertices: vec![vertex(0.0, 0.0), vertex(1.0, 0.0), vertex(0.0, 1.0)],
+            indices: vec![0, 1, 2],
+            middle_point: Vector3::from([[0.5], [0.5], [0.0]]),
+            x_half_size: 0.5,
+            y_half_size: 0.5,
+            z_half_size: 0.0,
+        }
+    }
+
+    fn baked(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("doem-{}-{}.dmesh", name, std::process::id()));
+        triangle().save_baked(&path).unwrap();
+        path
+    }
+
+    // Two triangles of a quad, split in the middle by a third vertex close to a corner
+    fn quad() -> ObjLoader {
+        let vertex = |x: f32, y: f32| Vertex {
+            pos: VertexPosition::new([x, y, 0.0]),
+            color: VertexColor::new([1.0, 1.0, 1.0]),
+        };
+        ObjLoader {
+            mode: Mode::Triangle,
+            vertices: vec![
+                vertex(-1.0, -1.0),
+                vertex(1.0, -1.0),
+                vertex(1.0, 1.0),
+                vertex(-1.0, 1.0),
+                vertex(0.9, 0.9),
+            ],
+            indices: vec![0, 1, 2, 0, 2, 3, 2, 4, 3],
+            middle_point: Vector3::origin(),
+            x_half_size: 1.0,
+            y_half_size: 1.0,
+            z_half_size: 0.0,
+        }
+    }
+
+    #[test]
+    fn simplify_merges_close_vertices() {
+        let simplified = quad().simplify(3);
+        assert_eq!(simplified.vertices.len(), 4);
+        // The triangle between the corner and its close neighbour collapses
+        assert_eq!(simplified.indices.len(), 6);
+        // Merged vertices end up at their average
+        let corner = *simplified.vertices[2].pos;
+        assert!((corner[0] - 0.95).abs() < 0.0001 && (corner[1] - 0.95).abs() < 0.0001);
+        assert_eq!(simplified.x_half_size, 1.0);
+    }
+
+    #[test]
+    fn simplify_keeps_meshes_finer_than_the_grid() {
+        let simplified = quad().simplify(64);
+        assert_eq!(simplified.vertices.len(), 5);
+        assert_eq!(simplified.indices, quad().indices);
+    }
+
+    #[test]
+    fn detail_paths() {
+        let path = ObjLoader::detail_path("models/donut.obj", 16);
+        assert_eq!(path, "models/donut.obj#16");
+        assert_eq!(ObjLoader::detail_source(&path), "models/donut.obj");
+        assert_eq!(
+            ObjLoader::detail_source("models/donut.obj"),
+            "models/donut.obj"
+        );
+        match ObjLoader::load_detail("models/donut.obj#many") {
+            Err(AssetError::InvalidDetail(_)) => (),
+            _ => panic!("the number of cells should be checked"),
+        }
+    }
+
+    #[test]
+    fn baked_mesh_round_trips() {
+        let path = baked("round-trip");
+        let loaded = ObjLoader::load_baked(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let original = triangle();
+        assert_eq!(loaded.indices, original.indices);
+        assert_eq!(loaded.vertices.len(), 3);
+        assert_eq!(*loaded.vertices[1].pos, *original.vertices[1].pos);
+        assert_eq!(loaded.x_half_size, 0.5);
+        assert_eq!(loaded.middle_point[1][0], 0.5);
+    }
+
+    #[test]
+    fn truncated_baked_mesh_is_invalid() {
+        let path = baked("truncated");
+        let size = fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(size - 4)
+            .unwrap();
+        let result = ObjLoader::load_baked(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(AssetError::InvalidBakedMesh(_)) => (),
+            _ => panic!("a truncated mesh should be invalid"),
+        }
+    }
+
+    #[test]
+    fn huge_counts_are_invalid() {
+        let path = baked("huge-counts");
+        {
+            let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+            // The vertex count right after the magic, version, middle point and half sizes
+            file.seek(SeekFrom::Start(BAKED_HEADER_SIZE - 8)).unwrap();
+            file.write_all(&u32::max_value().to_le_bytes()).unwrap();
+        }
+        let result = ObjLoader::load_baked(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(AssetError::InvalidBakedMesh(_)) => (),
+            _ => panic!("counts larger than the file should be invalid"),
+        }
     }
 }
@@ -0,0 +1,24 @@
+// A small xorshift generator, good enough for effects and reproducible from its seed
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck on a zero state
+        Self { state: seed.max(1) }
+    }
+    pub fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 32) as u32
+    }
+    // Between 0 and 1
+    pub fn next_f32(&mut self) -> f32 {
+        self.next_u32() as f32 / u32::max_value() as f32
+    }
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+}
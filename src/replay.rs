@@ -0,0 +1,530 @@
+use crate::ecs::components::ai_pilot::{AiPilot, Steering};
+use crate::ecs::components::armor::Armor;
+use crate::ecs::components::collider::Collider;
+use crate::ecs::components::damage::Damage;
+use crate::ecs::components::despawn::Despawn;
+use crate::ecs::components::fire_intent::FireIntent;
+use crate::ecs::components::gun::Gun;
+use crate::ecs::components::health::Health;
+use crate::ecs::components::homing::Homing;
+use crate::ecs::components::invulnerable::Invulnerable;
+use crate::ecs::components::lifetime::Lifetime;
+use crate::ecs::components::lives::Lives;
+use crate::ecs::components::physics::Physics;
+use crate::ecs::components::pulsate::Pulsate;
+use crate::ecs::components::shield::Shield;
+use crate::ecs::components::thruster::Thruster;
+use crate::ecs::components::transform::Transform;
+use crate::ecs::resources::game_state::{GameState, RespawnPoint};
+use crate::ecs::resources::score::Score;
+use crate::ecs::resources::time_scale::TimeScale;
+use doem_math::{Matrix4, Vector3};
+use glfw::Modifiers;
+use luminance_glfw::{Action, Key, WindowEvent};
+use specs::prelude::*;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fs;
+use std::hash::Hasher;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const HEADER: &str = "doem-replay 2";
+// The keys the systems react to, the others can not change the world
+const KEYS: [Key; 25] = [
+    Key::W,
+    Key::A,
+    Key::S,
+    Key::D,
+    Key::Q,
+    Key::E,
+    Key::R,
+    Key::F,
+    Key::Z,
+    Key::X,
+    Key::H,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::N,
+    Key::Equal,
+    Key::Minus,
+    Key::Left,
+    Key::Right,
+    Key::PageUp,
+    Key::PageDown,
+    Key::LeftShift,
+    Key::LeftControl,
+    Key::Space,
+    Key::Tab,
+];
+const ACTIONS: [Action; 3] = [Action::Press, Action::Release, Action::Repeat];
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(String, io::Error),
+    Syntax { line: usize, text: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(path, e) => write!(f, "cannot access {}: {}", path, e),
+            ReplayError::Syntax { line, text } => {
+                write!(f, "line {} is not a valid replay line: `{}`", line, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+// What the systems got to see during one tick, and the state of the world after it
+pub struct Tick {
+    pub events: Vec<WindowEvent>,
+    pub commands: Vec<String>,
+    pub hash: u64,
+}
+
+// A run of the game which can be played again to the same state: the scene it started with,
+// the weapon definitions, the seed of the particles and the input of every tick
+pub struct Replay {
+    pub seed: u64,
+    pub stress_bullets: usize,
+    // The whole weapons file, so that editing it does not change the replay
+    pub weapons: String,
+    pub ticks: Vec<Tick>,
+}
+
+pub enum ReplayMode {
+    Off,
+    Record(PathBuf, Replay),
+    Play(Replay),
+}
+
+impl ReplayMode {
+    pub fn is_on(&self) -> bool {
+        match self {
+            ReplayMode::Off => false,
+            _ => true,
+        }
+    }
+    pub fn seed(&self) -> Option<u64> {
+        match self {
+            ReplayMode::Off => None,
+            ReplayMode::Record(_, replay) | ReplayMode::Play(replay) => Some(replay.seed),
+        }
+    }
+}
+
+impl Replay {
+    // A new recording with a seed taken from the clock
+    pub fn new(stress_bullets: usize) -> Replay {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(1);
+        Replay {
+            seed,
+            stress_bullets,
+            weapons: String::new(),
+            ticks: Vec::new(),
+        }
+    }
+    // Only the key presses the systems listen to are kept
+    pub fn record(&mut self, events: &[WindowEvent], commands: &[String], hash: u64) {
+        let events = events
+            .iter()
+            .filter(|e| match e {
+                WindowEvent::Key(key, ..) => KEYS.contains(key),
+                _ => false,
+            })
+            .cloned()
+            .collect();
+        self.ticks.push(Tick {
+            events,
+            commands: commands.to_vec(),
+            hash,
+        });
+    }
+    pub fn load<P>(path: P) -> Result<Replay, ReplayError>
+    where
+        P: AsRef<Path>,
+    {
+        let path_name = path.as_ref().display().to_string();
+        let text = fs::read_to_string(&path).map_err(|e| ReplayError::Io(path_name, e))?;
+        let mut replay = Replay {
+            seed: 0,
+            stress_bullets: 0,
+            weapons: String::new(),
+            ticks: Vec::new(),
+        };
+        let mut events: Vec<WindowEvent> = Vec::new();
+        let mut commands: Vec<String> = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let syntax = || ReplayError::Syntax {
+                line: i + 1,
+                text: line.to_owned(),
+            };
+            if i == 0 {
+                if line != HEADER {
+                    return Err(syntax());
+                }
+                continue;
+            }
+            if line.starts_with("weapons ") {
+                replay.weapons.push_str(&line["weapons ".len()..]);
+                replay.weapons.push('\n');
+                continue;
+            }
+            if line.starts_with("command ") {
+                commands.push(line["command ".len()..].to_owned());
+                continue;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["seed", seed] => replay.seed = seed.parse().map_err(|_| syntax())?,
+                ["stress-bullets", count] => {
+                    replay.stress_bullets = count.parse().map_err(|_| syntax())?
+                }
+                ["key", key, scancode, action, modifiers] => {
+                    let key = KEYS.iter().find(|k| format!("{:?}", k) == *key);
+                    let action = ACTIONS.iter().find(|a| format!("{:?}", a) == *action);
+                    match (key, action, scancode.parse(), modifiers.parse()) {
+                        (Some(key), Some(action), Ok(scancode), Ok(modifiers)) => {
+                            events.push(WindowEvent::Key(
+                                *key,
+                                scancode,
+                                *action,
+                                Modifiers::from_bits_truncate(modifiers),
+                            ))
+                        }
+                        _ => return Err(syntax()),
+                    }
+                }
+                ["tick", hash] => replay.ticks.push(Tick {
+                    events: events.drain(..).collect(),
+                    commands: commands.drain(..).collect(),
+                    hash: u64::from_str_radix(*hash, 16).map_err(|_| syntax())?,
+                }),
+                [] => (),
+                _ => return Err(syntax()),
+            }
+        }
+        Ok(replay)
+    }
+    pub fn save<P>(&self, path: P) -> Result<(), ReplayError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut lines = vec![
+            HEADER.to_owned(),
+            format!("seed {}", self.seed),
+            format!("stress-bullets {}", self.stress_bullets),
+        ];
+        for line in self.weapons.lines() {
+            lines.push(format!("weapons {}", line));
+        }
+        for tick in &self.ticks {
+            for event in &tick.events {
+                if let WindowEvent::Key(key, scancode, action, modifiers) = event {
+                    lines.push(format!(
+                        "key {:?} {} {:?} {}",
+                        key,
+                        scancode,
+                        action,
+                        modifiers.bits()
+                    ));
+                }
+            }
+            for command in &tick.commands {
+                lines.push(format!("command {}", command));
+            }
+            lines.push(format!("tick {:016x}", tick.hash));
+        }
+        lines.push(String::new());
+        fs::write(&path, lines.join("\n"))
+            .map_err(|e| ReplayError::Io(path.as_ref().display().to_string(), e))
+    }
+}
+
+// Feeds the floats bit for bit, so that any difference between two runs changes the hash
+struct StateHasher(DefaultHasher);
+
+impl StateHasher {
+    fn float(&mut self, value: f32) {
+        self.0.write_u32(value.to_bits());
+    }
+    fn vector(&mut self, vector: &Vector3) {
+        for i in 0..3 {
+            self.float(vector[i][0]);
+        }
+    }
+    fn matrix(&mut self, matrix: &Matrix4) {
+        for i in 0..4 {
+            for j in 0..4 {
+                self.float(matrix[i][j]);
+            }
+        }
+    }
+    fn entity(&mut self, entity: Entity) {
+        self.0.write_u32(entity.id());
+        self.0.write_i32(entity.gen().id());
+    }
+    fn flag(&mut self, flag: bool) {
+        self.0.write_u8(flag as u8);
+    }
+    // Marks whether the entity has the component, so that adding or removing one is seen too
+    fn component<T, F>(&mut self, component: Option<&T>, hash: F)
+    where
+        F: FnOnce(&mut Self, &T),
+    {
+        self.flag(component.is_some());
+        if let Some(component) = component {
+            hash(self, component);
+        }
+    }
+    fn resource<T, F>(&mut self, world: &World, hash: F)
+    where
+        T: Send + Sync + 'static,
+        F: FnOnce(&mut Self, &T),
+    {
+        let resource = world.try_fetch::<T>();
+        self.component(resource.as_deref(), hash);
+    }
+}
+
+// Every component and resource the simulation reads or changes, bit for bit. What only
+// changes the picture, like the shapes, the particles and the camera, is left out.
+pub fn state_hash(world: &World) -> u64 {
+    let entities = world.entities();
+    let transform = world.read_storage::<Transform>();
+    let physics = world.read_storage::<Physics>();
+    let collider = world.read_storage::<Collider>();
+    let pulsate = world.read_storage::<Pulsate>();
+    let thruster = world.read_storage::<Thruster>();
+    let health = world.read_storage::<Health>();
+    let shield = world.read_storage::<Shield>();
+    let armor = world.read_storage::<Armor>();
+    let invulnerable = world.read_storage::<Invulnerable>();
+    let lives = world.read_storage::<Lives>();
+    let damage = world.read_storage::<Damage>();
+    let despawn = world.read_storage::<Despawn>();
+    let lifetime = world.read_storage::<Lifetime>();
+    let gun = world.read_storage::<Gun>();
+    let fire_intent = world.read_storage::<FireIntent>();
+    let ai_pilot = world.read_storage::<AiPilot>();
+    let homing = world.read_storage::<Homing>();
+    let mut hasher = StateHasher(DefaultHasher::new());
+    for e in (&*entities).join() {
+        hasher.entity(e);
+        hasher.component(transform.get(e), |h, t| {
+            h.vector(&t.position);
+            h.vector(&t.scale);
+            h.matrix(&t.orientation);
+        });
+        hasher.component(physics.get(e), |h, p| h.vector(&p.velocity));
+        hasher.component(collider.get(e), |h, c| h.vector(&c.half_size));
+        hasher.component(pulsate.get(e), |h, p| {
+            h.vector(&p.speed);
+            h.flag(p.current_direction);
+            h.vector(&p.max_scale);
+            h.vector(&p.min_scale);
+        });
+        hasher.component(thruster.get(e), |h, t| h.vector(&t.power));
+        hasher.component(health.get(e), |h, health| h.float(health.health));
+        hasher.component(shield.get(e), |h, s| {
+            h.float(s.max);
+            h.float(s.current);
+            h.float(s.regeneration);
+            h.float(s.delay);
+            h.float(s.since_hit);
+        });
+        hasher.component(armor.get(e), |h, a| {
+            h.float(a.resistances.kinetic);
+            h.float(a.resistances.energy);
+            h.float(a.resistances.explosive);
+            h.float(a.reduction);
+            h.float(a.invulnerability);
+        });
+        hasher.component(invulnerable.get(e), |h, i| h.float(i.ticks));
+        hasher.component(lives.get(e), |h, l| {
+            h.0.write_u32(l.remaining);
+            h.float(l.health);
+        });
+        hasher.component(damage.get(e), |h, d| {
+            h.float(d.damage);
+            h.0.write_u8(d.damage_type as u8);
+            h.flag(d.despawn_entity_on_impact);
+            h.entity(d.damage_dealer);
+        });
+        hasher.component(despawn.get(e), |_, _| ());
+        hasher.component(lifetime.get(e), |h, l| {
+            h.component(l.max_age.as_ref(), |h, age| h.float(*age));
+            h.component(l.max_distance.as_ref(), |h, distance| h.float(*distance));
+            h.float(l.age);
+            h.float(l.travelled);
+            h.component(l.last_position.as_ref(), |h, p| h.vector(p));
+        });
+        hasher.component(gun.get(e), |h, g| {
+            h.0.write(g.weapon.name.as_bytes());
+            h.float(g.cooldown);
+            h.0.write_u32(g.ammo);
+            h.float(g.reloading);
+        });
+        hasher.component(fire_intent.get(e), |h, f| h.flag(f.firing));
+        hasher.component(ai_pilot.get(e), |h, p| {
+            match p.steering {
+                Steering::Seek => h.0.write_u8(0),
+                Steering::Flee => h.0.write_u8(1),
+                Steering::Pursue => h.0.write_u8(2),
+                Steering::Arrive => h.0.write_u8(3),
+                Steering::Orbit { radius } => {
+                    h.0.write_u8(4);
+                    h.float(radius);
+                }
+            }
+            h.component(p.target.as_ref(), |h, target| h.entity(*target));
+            h.float(p.max_speed);
+            h.float(p.turn_rate);
+            h.float(p.slowing_radius);
+        });
+        hasher.component(homing.get(e), |h, homing| {
+            h.entity(homing.target);
+            h.float(homing.turn_rate);
+        });
+    }
+    hasher.resource(world, |h, state: &GameState| h.flag(state.game_over));
+    hasher.resource(world, |h, score: &Score| h.0.write_u32(score.kills));
+    hasher.resource(world, |h, scale: &TimeScale| h.float(scale.0));
+    hasher.resource(world, |h, point: &RespawnPoint| {
+        h.vector(&point.position);
+        h.matrix(&point.orientation);
+    });
+    hasher.0.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::world::DoemWorld;
+    use crate::weapons::Weapon;
+    use std::env;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let replay = Replay {
+            seed: 1234,
+            stress_bullets: 10,
+            weapons: "[laser]\n# fast\n\nspeed = 9\n".to_owned(),
+            ticks: vec![
+                Tick {
+                    events: vec![WindowEvent::Key(
+                        Key::W,
+                        17,
+                        Action::Press,
+                        Modifiers::Shift,
+                    )],
+                    commands: vec!["spawn enemy seek".to_owned()],
+                    hash: 0xdead_beef,
+                },
+                Tick {
+                    events: Vec::new(),
+                    commands: Vec::new(),
+                    hash: u64::max_value(),
+                },
+                Tick {
+                    events: vec![WindowEvent::Key(
+                        Key::Space,
+                        57,
+                        Action::Release,
+                        Modifiers::empty(),
+                    )],
+                    commands: Vec::new(),
+                    hash: 0,
+                },
+            ],
+        };
+        let path = env::temp_dir().join(format!("doem-replay-{}.txt", std::process::id()));
+        replay.save(&path).unwrap();
+        let loaded = Replay::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.seed, replay.seed);
+        assert_eq!(loaded.stress_bullets, replay.stress_bullets);
+        assert_eq!(loaded.weapons, replay.weapons);
+        assert_eq!(loaded.ticks.len(), replay.ticks.len());
+        for (loaded, tick) in loaded.ticks.iter().zip(replay.ticks.iter()) {
+            assert_eq!(loaded.events, tick.events);
+            assert_eq!(loaded.commands, tick.commands);
+            assert_eq!(loaded.hash, tick.hash);
+        }
+    }
+
+    #[test]
+    fn load_rejects_other_files() {
+        let path = env::temp_dir().join(format!("doem-not-replay-{}.txt", std::process::id()));
+        fs::write(&path, "doem-replay 2\nseed 1\nkey W 17 Hold 0\n").unwrap();
+        let result = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        match result {
+            Err(ReplayError::Syntax { line: 3, .. }) => (),
+            _ => panic!("an unknown action should be a syntax error"),
+        }
+    }
+
+    fn ship_world() -> (World, Entity) {
+        let mut world = DoemWorld::new();
+        world.insert(Score::default());
+        world.insert(GameState::default());
+        let ship = world
+            .create_entity()
+            .with(Health { health: 100.0 })
+            .with(Gun::new(Weapon::new("laser")))
+            .with(Shield::new(50.0, 1.0, 60.0))
+            .build();
+        (world, ship)
+    }
+
+    #[test]
+    fn hash_is_stable() {
+        assert_eq!(state_hash(&ship_world().0), state_hash(&ship_world().0));
+    }
+
+    #[test]
+    fn hash_covers_components_and_resources() {
+        let (mut world, ship) = ship_world();
+        let mut hashes = vec![state_hash(&world)];
+        world.write_storage::<Gun>().get_mut(ship).unwrap().cooldown = 3.0;
+        hashes.push(state_hash(&world));
+        world
+            .write_storage::<Shield>()
+            .get_mut(ship)
+            .unwrap()
+            .since_hit = 0.0;
+        hashes.push(state_hash(&world));
+        world
+            .write_storage::<Invulnerable>()
+            .insert(ship, Invulnerable { ticks: 10.0 })
+            .unwrap();
+        hashes.push(state_hash(&world));
+        world
+            .write_storage::<FireIntent>()
+            .insert(ship, FireIntent::default())
+            .unwrap();
+        hashes.push(state_hash(&world));
+        world.write_resource::<Score>().kills += 1;
+        hashes.push(state_hash(&world));
+        world.write_resource::<GameState>().game_over = true;
+        hashes.push(state_hash(&world));
+        world.insert(TimeScale(0.5));
+        hashes.push(state_hash(&world));
+        for (i, hash) in hashes.iter().enumerate() {
+            assert!(
+                !hashes[i + 1..].contains(hash),
+                "change {} is not hashed",
+                i
+            );
+        }
+    }
+}
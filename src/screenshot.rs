@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+
+// How far a channel of a rendered frame can be off from the golden frame, drivers round
+// blending slightly differently
+pub const FRAME_TOLERANCE: u8 = 2;
+
+// What happens to every rendered frame
+#[derive(Clone, Debug)]
+pub enum Frames {
+    // Written as pngs into the directory
+    Render(PathBuf),
+    // Compared with the golden frames a Render run wrote into the directory
+    Compare(PathBuf),
+}
+
+impl Frames {
+    pub fn path(&self, frame: u64) -> PathBuf {
+        let dir = match self {
+            Frames::Render(dir) | Frames::Compare(dir) => dir,
+        };
+        dir.join(format!("frame-{:06}.png", frame))
+    }
+}
+
+// Writes the RGBA pixels read back from GL as a png. GL stores the bottom row first, so the
+// rows are flipped.
+pub fn save_png<P>(path: P, width: u32, height: u32, rgba: &[u8]) -> io::Result<()>
+where
+    P: AsRef<Path>,
+{
+    let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), width, height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&flip_rows(width, rgba))?;
+    Ok(())
+}
+
+// The RGBA pixels of an 8 bit RGBA png, top row first
+pub fn load_png<P>(path: P) -> io::Result<(u32, u32, Vec<u8>)>
+where
+    P: AsRef<Path>,
+{
+    let decoder = png::Decoder::new(File::open(path)?);
+    let (info, mut reader) = decoder.read_info()?;
+    if info.color_type != png::ColorType::RGBA || info.bit_depth != png::BitDepth::Eight {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "only 8 bit RGBA pngs can be compared",
+        ));
+    }
+    let mut rgba = vec![0; info.buffer_size()];
+    reader.next_frame(&mut rgba)?;
+    Ok((info.width, info.height, rgba))
+}
+
+// The number of pixels with a channel further apart than the tolerance
+pub fn differing_pixels(a: &[u8], b: &[u8], tolerance: u8) -> usize {
+    let differing = a
+        .chunks(4)
+        .zip(b.chunks(4))
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(b.iter())
+                .any(|(a, b)| (i16::from(*a) - i16::from(*b)).abs() > i16::from(tolerance))
+        })
+        .count();
+    // Every pixel one of them does not have differs too
+    differing + (a.len().max(b.len()) - a.len().min(b.len())) / 4
+}
+
+// GL stores the bottom row first, pngs the top row
+pub fn flip_rows(width: u32, rgba: &[u8]) -> Vec<u8> {
+    let row_size = (width as usize * 4).max(1);
+    rgba.chunks(row_size).rev().flatten().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn decode(path: &PathBuf) -> (u32, u32, Vec<u8>) {
+        load_png(path).unwrap()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("doem-{}-{}.png", name, std::process::id()))
+    }
+
+    #[test]
+    fn small_image_round_trips_upside_down() {
+        let bottom = [255, 0, 0, 255, 0, 255, 0, 128];
+        let top = [0, 0, 255, 0, 10, 20, 30, 40];
+        let rgba: Vec<u8> = bottom.iter().chain(top.iter()).cloned().collect();
+        let path = temp_path("small");
+        save_png(&path, 2, 2, &rgba).unwrap();
+        let (width, height, pixels) = decode(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(&pixels[..8], &top);
+        assert_eq!(&pixels[8..], &bottom);
+    }
+
+    #[test]
+    fn frames_are_compressed() {
+        let (width, height) = (320, 240);
+        let rgba: Vec<u8> = (0..width * height * 4).map(|i| (i / 4 % 7) as u8).collect();
+        let path = temp_path("compressed");
+        save_png(&path, width, height, &rgba).unwrap();
+        let size = fs::metadata(&path).unwrap().len();
+        let (_, _, pixels) = decode(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(size < rgba.len() as u64 / 10);
+        assert_eq!(pixels, flip_rows(width, &rgba));
+    }
+
+    #[test]
+    fn differing_pixels_within_the_tolerance() {
+        let frame = [10, 20, 30, 255, 0, 0, 0, 255];
+        assert_eq!(differing_pixels(&frame, &frame, 0), 0);
+        let close = [12, 18, 30, 255, 0, 0, 0, 255];
+        assert_eq!(differing_pixels(&frame, &close, 2), 0);
+        assert_eq!(differing_pixels(&frame, &close, 1), 1);
+        let far = [10, 20, 30, 255, 0, 0, 200, 255];
+        assert_eq!(differing_pixels(&frame, &far, FRAME_TOLERANCE), 1);
+        assert_eq!(differing_pixels(&frame, &frame[..4], 0), 1);
+    }
+
+    #[test]
+    fn frame_paths() {
+        let frames = Frames::Compare(PathBuf::from("golden"));
+        assert_eq!(frames.path(12), Path::new("golden/frame-000012.png"));
+    }
+}
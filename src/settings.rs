@@ -0,0 +1,308 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const SETTINGS_PATH: &str = "settings.cfg";
+// The settings file and the command line flags share these keys
+pub const KEYS: [(&str, &str); 9] = [
+    ("width", "The width of the window in pixels"),
+    ("height", "The height of the window in pixels"),
+    (
+        "fullscreen",
+        "Opens the window fullscreen on the primary monitor, true or false",
+    ),
+    (
+        "vsync",
+        "Waits for the vertical refresh before showing a frame, true or false",
+    ),
+    ("fov", "The vertical field of view in degrees"),
+    ("z-near", "The distance to the near clip plane"),
+    ("z-far", "The distance to the far clip plane"),
+    (
+        "msaa",
+        "The number of samples per pixel, 0 turns multisampling off",
+    ),
+    (
+        "hud-scale",
+        "The size of the HUD in pixels per font pixel, 0 hides the HUD",
+    ),
+];
+const MSAA_SAMPLES: [u32; 5] = [0, 2, 4, 8, 16];
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(String, io::Error),
+    Syntax { line: usize, text: String },
+    UnknownKey(String),
+    InvalidValue { key: String, value: String },
+    Invalid(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(path, e) => write!(f, "cannot read {}: {}", path, e),
+            SettingsError::Syntax { line, text } => write!(
+                f,
+                "line {} should look like `key = value`, found `{}`",
+                line, text
+            ),
+            SettingsError::UnknownKey(key) => write!(
+                f,
+                "unknown setting `{}`, known settings are: {}",
+                key,
+                KEYS.iter()
+                    .map(|(key, _)| *key)
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            ),
+            SettingsError::InvalidValue { key, value } => {
+                write!(f, "`{}` is not a valid value for `{}`", value, key)
+            }
+            SettingsError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    // The vertical field of view in degrees
+    pub fov: f32,
+    pub z_near: f32,
+    pub z_far: f32,
+    // 0 turns multisampling off
+    pub msaa: u32,
+    // Screen pixels per HUD font pixel, 0 hides the HUD
+    pub hud_scale: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            width: 1600,
+            height: 900,
+            fullscreen: false,
+            vsync: true,
+            fov: 90.0,
+            z_near: 0.1,
+            z_far: 100_000.0,
+            msaa: 0,
+            hud_scale: 2.0,
+        }
+    }
+}
+
+impl Settings {
+    // Reads `key = value` lines, everything after a # is a comment
+    pub fn load<P>(path: P) -> Result<Self, SettingsError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| SettingsError::Io(path.display().to_string(), e))?;
+        Self::parse(&source)
+    }
+    pub fn parse(source: &str) -> Result<Self, SettingsError> {
+        let mut settings = Self::default();
+        for (i, line) in source.lines().enumerate() {
+            let text = line.split('#').next().unwrap_or("").trim();
+            if text.is_empty() {
+                continue;
+            }
+            let mut parts = text.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => settings.set(key.trim(), value.trim())?,
+                _ => {
+                    return Err(SettingsError::Syntax {
+                        line: i + 1,
+                        text: text.to_owned(),
+                    })
+                }
+            }
+        }
+        Ok(settings)
+    }
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingsError> {
+        let invalid = || SettingsError::InvalidValue {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        };
+        match key {
+            "width" => self.width = value.parse().map_err(|_| invalid())?,
+            "height" => self.height = value.parse().map_err(|_| invalid())?,
+            "fullscreen" => self.fullscreen = Self::parse_bool(value).ok_or_else(invalid)?,
+            "vsync" => self.vsync = Self::parse_bool(value).ok_or_else(invalid)?,
+            "fov" => self.fov = value.parse().map_err(|_| invalid())?,
+            "z-near" => self.z_near = value.parse().map_err(|_| invalid())?,
+            "z-far" => self.z_far = value.parse().map_err(|_| invalid())?,
+            "msaa" => self.msaa = value.parse().map_err(|_| invalid())?,
+            "hud-scale" => self.hud_scale = value.parse().map_err(|_| invalid())?,
+            _ => return Err(SettingsError::UnknownKey(key.to_owned())),
+        }
+        Ok(())
+    }
+    fn parse_bool(value: &str) -> Option<bool> {
+        match value {
+            "true" | "on" | "yes" | "1" => Some(true),
+            "false" | "off" | "no" | "0" => Some(false),
+            _ => None,
+        }
+    }
+    pub fn validate(&self) -> Result<(), SettingsError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(SettingsError::Invalid(format!(
+                "the resolution {}x{} has to be at least 1x1",
+                self.width, self.height
+            )));
+        }
+        if !(self.fov > 0.0 && self.fov < 180.0) {
+            return Err(SettingsError::Invalid(format!(
+                "the field of view {} has to be between 0 and 180 degrees",
+                self.fov
+            )));
+        }
+        if !(self.z_near > 0.0 && self.z_near < self.z_far) || !self.z_far.is_finite() {
+            return Err(SettingsError::Invalid(format!(
+                "the clip planes need 0 < z-near < z-far, found z-near {} and z-far {}",
+                self.z_near, self.z_far
+            )));
+        }
+        if !MSAA_SAMPLES.contains(&self.msaa) {
+            return Err(SettingsError::Invalid(format!(
+                "msaa {} is not supported, use one of {:?}",
+                self.msaa, MSAA_SAMPLES
+            )));
+        }
+        if !(self.hud_scale >= 0.0 && self.hud_scale.is_finite()) {
+            return Err(SettingsError::Invalid(format!(
+                "the hud scale {} has to be 0 or more",
+                self.hud_scale
+            )));
+        }
+        Ok(())
+    }
+    pub fn fov_radians(&self) -> f32 {
+        self.fov.to_radians()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(settings: Settings) -> String {
+        match settings.validate() {
+            Err(SettingsError::Invalid(message)) => message,
+            _ => panic!("{:?} should be invalid", settings),
+        }
+    }
+
+    #[test]
+    fn parse_settings() {
+        let source = "# comment
+width = 800
+height=600 # inline
+vsync = off
+msaa = 4
+";
+        let settings = Settings::parse(source).unwrap();
+        assert_eq!((settings.width, settings.height), (800, 600));
+        assert!(!settings.vsync);
+        assert_eq!(settings.msaa, 4);
+        assert_eq!(settings.fov, Settings::default().fov);
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn flags_override_the_file() {
+        let mut settings = Settings::parse("width = 800\n").unwrap();
+        settings.set("width", "1024").unwrap();
+        settings.set("fullscreen", "yes").unwrap();
+        assert_eq!(settings.width, 1024);
+        assert!(settings.fullscreen);
+    }
+
+    #[test]
+    fn unknown_key() {
+        match Settings::parse("width = 800\nresolution = 800x600\n") {
+            Err(SettingsError::UnknownKey(key)) => assert_eq!(key, "resolution"),
+            _ => panic!("an unknown key should be rejected"),
+        }
+    }
+
+    #[test]
+    fn bad_values() {
+        for line in &["width = -1", "vsync = maybe", "fov = wide", "msaa = 4.5"] {
+            match Settings::parse(line) {
+                Err(SettingsError::InvalidValue { .. }) => (),
+                _ => panic!("`{}` should be rejected", line),
+            }
+        }
+        match Settings::parse("\nwidth 800\n") {
+            Err(SettingsError::Syntax { line: 2, .. }) => (),
+            _ => panic!("a line without = should be rejected"),
+        }
+    }
+
+    #[test]
+    fn zero_resolution() {
+        let message = invalid(Settings {
+            height: 0,
+            ..Settings::default()
+        });
+        assert!(message.contains("1600x0"));
+    }
+
+    #[test]
+    fn clip_planes() {
+        invalid(Settings {
+            z_near: 100.0,
+            z_far: 100.0,
+            ..Settings::default()
+        });
+        invalid(Settings {
+            z_near: 0.0,
+            ..Settings::default()
+        });
+        invalid(Settings {
+            z_far: std::f32::INFINITY,
+            ..Settings::default()
+        });
+    }
+
+    #[test]
+    fn msaa_samples() {
+        let message = invalid(Settings {
+            msaa: 3,
+            ..Settings::default()
+        });
+        assert!(message.contains("msaa 3"));
+        for samples in MSAA_SAMPLES.iter() {
+            let settings = Settings {
+                msaa: *samples,
+                ..Settings::default()
+            };
+            assert!(settings.validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn field_of_view_and_hud_scale() {
+        invalid(Settings {
+            fov: 180.0,
+            ..Settings::default()
+        });
+        invalid(Settings {
+            hud_scale: -1.0,
+            ..Settings::default()
+        });
+    }
+}
@@ -0,0 +1,9 @@
+in vec2 v_uv;
+
+out vec4 frag;
+
+uniform sampler2D source;
+
+void main() {
+  frag = texture(source, v_uv);
+}
@@ -0,0 +1,9 @@
+out vec2 v_uv;
+
+const vec2[4] QUAD = vec2[](vec2(-1., -1.), vec2(1., -1.), vec2(1., 1.), vec2(-1., 1.));
+
+void main() {
+  vec2 p = QUAD[gl_VertexID];
+  gl_Position = vec4(p, 0., 1.);
+  v_uv = p * .5 + .5;
+}
@@ -1,13 +1,17 @@
 in vec3 pos;
 in vec3 color;
+in vec4 model_x;
+in vec4 model_y;
+in vec4 model_z;
+in vec4 model_w;
 
 out vec3 v_color;
 
-uniform mat4 transform;
 uniform mat4 projection;
 uniform mat4 view;
 
 void main() {
+  mat4 transform = mat4(model_x, model_y, model_z, model_w);
   gl_Position = projection * view * transform * vec4(pos, 1.0f);
   v_color = color;
 }
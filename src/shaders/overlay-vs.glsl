@@ -0,0 +1,15 @@
+in vec2 corner;
+in vec4 rect;
+in vec4 color;
+
+out vec4 v_color;
+
+uniform vec2 screen_size;
+
+void main() {
+  // The rect is in pixels with the origin at the top left, GL has its origin at the bottom left
+  vec2 pixel = rect.xy + (corner * .5 + .5) * rect.zw;
+  vec2 ndc = pixel / screen_size * 2. - 1.;
+  gl_Position = vec4(ndc.x, -ndc.y, 0., 1.);
+  v_color = color;
+}
@@ -0,0 +1,10 @@
+in vec2 v_corner;
+in vec4 v_color;
+
+out vec4 frag;
+
+void main() {
+  // A soft round dot instead of a square
+  float falloff = 1. - smoothstep(.5, 1., length(v_corner));
+  frag = vec4(v_color.rgb, v_color.a * falloff);
+}
@@ -0,0 +1,20 @@
+in vec2 corner;
+in vec3 center;
+in vec4 color;
+in float size;
+
+out vec2 v_corner;
+out vec4 v_color;
+
+uniform mat4 projection;
+uniform mat4 view;
+
+void main() {
+  // The rows of the view rotation are the camera's right and up axes, so the quad faces it
+  vec3 right = vec3(view[0][0], view[1][0], view[2][0]);
+  vec3 up = vec3(view[0][1], view[1][1], view[2][1]);
+  vec3 pos = center + (right * corner.x + up * corner.y) * size * .5;
+  gl_Position = projection * view * vec4(pos, 1.);
+  v_corner = corner;
+  v_color = color;
+}
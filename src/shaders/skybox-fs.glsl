@@ -0,0 +1,9 @@
+in vec3 v_direction;
+
+out vec4 frag;
+
+uniform samplerCube skybox;
+
+void main() {
+  frag = vec4(texture(skybox, normalize(v_direction)).rgb, 1.);
+}
@@ -0,0 +1,15 @@
+out vec3 v_direction;
+
+uniform mat4 projection;
+uniform mat4 view;
+
+const vec2[4] QUAD = vec2[](vec2(-1., -1.), vec2(1., -1.), vec2(1., 1.), vec2(-1., 1.));
+
+void main() {
+  vec2 p = QUAD[gl_VertexID];
+  gl_Position = vec4(p, 0., 1.);
+  // Drop the translation of the view, so the skybox only follows the rotation of the camera
+  mat4 rotation = mat4(mat3(view));
+  vec4 far = inverse(projection * rotation) * vec4(p, 1., 1.);
+  v_direction = far.xyz / far.w;
+}
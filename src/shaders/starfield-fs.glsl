@@ -0,0 +1,7 @@
+in float v_brightness;
+
+out vec4 frag;
+
+void main() {
+  frag = vec4(vec3(v_brightness), 1.);
+}
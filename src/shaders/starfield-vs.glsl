@@ -0,0 +1,36 @@
+out float v_brightness;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform vec3 eye;
+uniform int stars_per_layer;
+uniform float layer_size;
+uniform float brightness;
+
+uint hash(uint x) {
+  x ^= x >> 16;
+  x *= 0x7feb352du;
+  x ^= x >> 15;
+  x *= 0x846ca68bu;
+  x ^= x >> 16;
+  return x;
+}
+
+float random(uint seed) {
+  return float(hash(seed)) / 4294967295.;
+}
+
+void main() {
+  int layer = gl_VertexID / stars_per_layer;
+  float size = layer_size * float(layer + 1);
+  uint id = uint(gl_VertexID) * 4u;
+  vec3 star = vec3(random(id), random(id + 1u), random(id + 2u)) * size;
+
+  // The stars repeat every size units, so the box of the layer always surrounds the camera
+  vec3 pos = eye - size * .5 + mod(star - eye + size * .5, size);
+  gl_Position = projection * view * vec4(pos, 1.);
+
+  // Fade the stars out towards the edge of the box, so they don't pop when they wrap around
+  float distance = length(pos - eye) / (size * .5);
+  v_brightness = brightness * (.3 + .7 * random(id + 3u)) * (1. - smoothstep(.6, 1., distance));
+}
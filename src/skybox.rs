@@ -0,0 +1,124 @@
+use crate::asset_error::AssetError;
+use std::fs;
+use std::path::Path;
+
+// In the order of the cubemap faces: +X, -X, +Y, -Y, +Z, -Z
+const FACE_NAMES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+const GRADIENT_SIZE: u32 = 64;
+
+// The RGB texels of the six faces of a square cubemap
+pub struct CubemapFaces {
+    pub size: u32,
+    pub faces: Vec<Vec<u8>>,
+}
+
+impl CubemapFaces {
+    pub fn load<P>(dir: P) -> Result<Self, AssetError>
+    where
+        P: AsRef<Path>,
+    {
+        let mut size = 0;
+        let mut faces: Vec<Vec<u8>> = Vec::with_capacity(FACE_NAMES.len());
+        for name in FACE_NAMES.iter() {
+            let path = dir.as_ref().join(format!("{}.ppm", name));
+            let (width, height, texels) = Self::load_ppm(&path)?;
+            if width != height || (size != 0 && width != size) {
+                return Err(AssetError::InvalidImage(format!(
+                    "{} is {}x{}, every face has to be the same square size",
+                    path.display(),
+                    width,
+                    height
+                )));
+            }
+            size = width;
+            faces.push(texels);
+        }
+        Ok(Self { size, faces })
+    }
+    // Reads a binary (P6) ppm with 8 bits per channel
+    fn load_ppm(path: &Path) -> Result<(u32, u32, Vec<u8>), AssetError> {
+        let bytes = fs::read(path)?;
+        let invalid =
+            || AssetError::InvalidImage(format!("{} is not a binary ppm", path.display()));
+
+        if !bytes.starts_with(b"P6") {
+            return Err(invalid());
+        }
+        // After the magic number follow the width, height and max value, separated by whitespace
+        let mut fields: Vec<u32> = Vec::new();
+        let mut pos = 2;
+        while fields.len() < 3 {
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            if pos < bytes.len() && bytes[pos] == b'#' {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            let start = pos;
+            while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                pos += 1;
+            }
+            let field = std::str::from_utf8(&bytes[start..pos])
+                .ok()
+                .and_then(|f| f.parse().ok())
+                .ok_or_else(invalid)?;
+            fields.push(field);
+        }
+        // A single whitespace character separates the header from the texels
+        pos += 1;
+        let (width, height, max_value) = (fields[0], fields[1], fields[2]);
+        if max_value != 255 {
+            return Err(AssetError::InvalidImage(format!(
+                "{} has to use 8 bits per channel",
+                path.display()
+            )));
+        }
+        let len = width as usize * height as usize * 3;
+        if bytes.len() < pos + len {
+            return Err(invalid());
+        }
+        Ok((width, height, bytes[pos..pos + len].to_vec()))
+    }
+    pub fn gradient(zenith: &[f32; 3], horizon: &[f32; 3], nadir: &[f32; 3]) -> Self {
+        let size = GRADIENT_SIZE;
+        let faces = (0..FACE_NAMES.len())
+            .map(|face| {
+                let mut texels: Vec<u8> = Vec::with_capacity((size * size * 3) as usize);
+                for t in 0..size {
+                    for s in 0..size {
+                        let up = Self::direction_y(face, s, t, size);
+                        let (from, to, amount) = if up >= 0.0 {
+                            (horizon, zenith, up)
+                        } else {
+                            (horizon, nadir, -up)
+                        };
+                        for c in 0..3 {
+                            let value = from[c] + (to[c] - from[c]) * amount;
+                            texels.push((value.max(0.0).min(1.0) * 255.0).round() as u8);
+                        }
+                    }
+                }
+                texels
+            })
+            .collect();
+        Self { size, faces }
+    }
+    // The height of the normalized direction a texel of a face points to, following the GL
+    // cubemap layout
+    fn direction_y(face: usize, s: u32, t: u32, size: u32) -> f32 {
+        let sc = 2.0 * (s as f32 + 0.5) / size as f32 - 1.0;
+        let tc = 2.0 * (t as f32 + 0.5) / size as f32 - 1.0;
+        let (x, y, z) = match face {
+            0 => (1.0, -tc, -sc),
+            1 => (-1.0, -tc, sc),
+            2 => (sc, 1.0, tc),
+            3 => (sc, -1.0, -tc),
+            4 => (sc, -tc, 1.0),
+            _ => (-sc, -tc, -1.0),
+        };
+        y / (x * x + y * y + z * z).sqrt()
+    }
+}
@@ -1,75 +1,355 @@
+use crate::asset_error::AssetError;
 use crate::data::AABB;
 use crate::ecs::components::shape::Shape;
+use crate::ecs::resources::asset_stats::AssetStats;
+use crate::gl_common::Instance;
 use crate::obj_loader::ObjLoader;
 use doem_math::Vector3;
 use luminance::tess::Tess;
 use luminance_glfw::GlfwSurface;
 use std::cell::RefCell;
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+const LOADER_THREADS: usize = 2;
+const INITIAL_INSTANCE_CAPACITY: usize = 1;
+
+type LoadResult = (String, Result<ObjLoader, AssetError>);
+// The bit patterns of an AABB's middle point and half size
+type AABBKey = [u32; 6];
+
+// A counted reference to a tesselation, shapes hold one for every mesh they use. The
+// TessManager keeps one itself and releases the tesselation once that is the last one left.
+#[derive(Clone, Debug)]
+pub struct TessHandle(Arc<usize>);
+
+impl TessHandle {
+    pub fn new(id: usize) -> Self {
+        TessHandle(Arc::new(id))
+    }
+    pub fn id(&self) -> usize {
+        *self.0
+    }
+    fn is_last(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+}
+
+// The source is kept around to rebuild the tesselation when its instance buffer is too small
+struct Mesh {
+    tess: Tess,
+    source: ObjLoader,
+    instance_capacity: usize,
+    handle: TessHandle,
+}
+
+// A loaded obj file, without a handle so the cache does not keep it alive
+#[derive(Clone)]
+struct Loaded {
+    id: usize,
+    bounding_box: AABB,
+}
 
 pub struct TessManager {
     surface: Rc<RefCell<GlfwSurface>>,
-    tesselations: Vec<Option<Tess>>,
-    path_index: HashMap<String, Shape>,
+    tesselations: Vec<Option<Mesh>>,
+    free_ids: Vec<usize>,
+    // Tesselations which are never released, like the placeholder and preloaded meshes
+    pinned: HashSet<usize>,
+    path_index: HashMap<String, Loaded>,
+    aabb_index: HashMap<AABBKey, usize>,
+    placeholder: Option<Loaded>,
+    pending: HashSet<String>,
+    preloaded: HashSet<String>,
+    reloading: HashSet<String>,
+    // Loads which were in flight when their obj file changed, they are requested again
+    stale: HashSet<String>,
+    load_requests: Sender<String>,
+    load_results: Receiver<LoadResult>,
 }
 
 impl TessManager {
     pub fn new(surface: Rc<RefCell<GlfwSurface>>) -> Self {
+        let (load_requests, requests) = mpsc::channel::<String>();
+        let (results, load_results) = mpsc::channel::<LoadResult>();
+        Self::spawn_loaders(Arc::new(Mutex::new(requests)), results);
+
         Self {
             surface,
-            tesselations: Vec::<Option<Tess>>::new(),
-            path_index: HashMap::<String, Shape>::new(),
+            tesselations: Vec::<Option<Mesh>>::new(),
+            free_ids: Vec::<usize>::new(),
+            pinned: HashSet::<usize>::new(),
+            path_index: HashMap::<String, Loaded>::new(),
+            aabb_index: HashMap::<AABBKey, usize>::new(),
+            placeholder: None,
+            pending: HashSet::<String>::new(),
+            preloaded: HashSet::<String>::new(),
+            reloading: HashSet::<String>::new(),
+            stale: HashSet::<String>::new(),
+            load_requests,
+            load_results,
         }
     }
-    pub fn get_tess(&mut self, id: usize) -> Option<&Tess> {
+    // The workers only parse the obj files, the GPU upload has to happen on the render thread
+    // and is done in poll_loaded
+    fn spawn_loaders(requests: Arc<Mutex<Receiver<String>>>, results: Sender<LoadResult>) {
+        for _ in 0..LOADER_THREADS {
+            let requests = requests.clone();
+            let results = results.clone();
+            thread::spawn(move || loop {
+                let request = requests.lock().unwrap().recv();
+                let obj_path = match request {
+                    Ok(obj_path) => obj_path,
+                    // The TessManager is gone, so nobody is waiting for meshes anymore
+                    Err(_) => break,
+                };
+                let loaded = ObjLoader::load_detail(&obj_path);
+                if results.send((obj_path, loaded)).is_err() {
+                    break;
+                }
+            });
+        }
+    }
+    pub fn get_tess(&self, id: usize) -> Option<&Tess> {
         if id >= self.tesselations.len() {
             return None;
         }
 
         match &self.tesselations[id] {
-            Some(tess) => Some(&tess),
+            Some(mesh) => Some(&mesh.tess),
             None => None,
         }
     }
-    pub fn get_aabb_id(&mut self, aabb: &AABB) -> usize {
-        let tess = ObjLoader::generate_aabb(aabb, &mut *self.surface.borrow_mut()).unwrap();
-        self.tesselations.push(Some(tess));
-        self.tesselations.len() - 1
+    // Uploads the transformations of the instances which are drawn with the tesselation
+    pub fn set_instances(&mut self, id: usize, instances: &[Instance]) -> Result<(), AssetError> {
+        let mesh = match self.tesselations.get_mut(id) {
+            Some(Some(mesh)) => mesh,
+            _ => return Ok(()),
+        };
+        if instances.len() > mesh.instance_capacity {
+            let instance_capacity = instances.len().next_power_of_two();
+            mesh.tess = mesh
+                .source
+                .build_tess(&mut *self.surface.borrow_mut(), instance_capacity)?;
+            mesh.instance_capacity = instance_capacity;
+        }
+        let mut slice = mesh.tess.as_inst_slice_mut::<Instance>()?;
+        slice[..instances.len()].copy_from_slice(instances);
+        Ok(())
+    }
+    // Shapes with bounding boxes of the same dimensions share one tesselation
+    pub fn get_aabb_tess(&mut self, aabb: &AABB) -> Result<TessHandle, AssetError> {
+        let key = Self::aabb_key(aabb);
+        if let Some(id) = self.aabb_index.get(&key) {
+            return Ok(self.handle(*id));
+        }
+
+        let id = self.insert_tess(ObjLoader::generate_aabb(aabb))?;
+        self.aabb_index.insert(key, id);
+        Ok(self.handle(id))
+    }
+    fn handle(&self, id: usize) -> TessHandle {
+        match &self.tesselations[id] {
+            Some(mesh) => mesh.handle.clone(),
+            None => panic!("tesselation {} was already released", id),
+        }
+    }
+    fn aabb_key(aabb: &AABB) -> AABBKey {
+        [
+            aabb.middle_point[0][0].to_bits(),
+            aabb.middle_point[1][0].to_bits(),
+            aabb.middle_point[2][0].to_bits(),
+            aabb.half_size[0][0].to_bits(),
+            aabb.half_size[1][0].to_bits(),
+            aabb.half_size[2][0].to_bits(),
+        ]
+    }
+    fn insert_tess(&mut self, source: ObjLoader) -> Result<usize, AssetError> {
+        let tess = source.build_tess(&mut *self.surface.borrow_mut(), INITIAL_INSTANCE_CAPACITY)?;
+        let id = match self.free_ids.pop() {
+            Some(id) => id,
+            None => {
+                self.tesselations.push(None);
+                self.tesselations.len() - 1
+            }
+        };
+        self.tesselations[id] = Some(Mesh {
+            tess,
+            source,
+            instance_capacity: INITIAL_INSTANCE_CAPACITY,
+            handle: TessHandle::new(id),
+        });
+        Ok(id)
+    }
+    // Frees the tesselations no shape holds a handle to anymore
+    pub fn release_unused(&mut self) {
+        for id in 0..self.tesselations.len() {
+            let unused = match &self.tesselations[id] {
+                Some(mesh) => mesh.handle.is_last() && !self.pinned.contains(&id),
+                None => false,
+            };
+            if unused {
+                self.tesselations[id] = None;
+                self.free_ids.push(id);
+            }
+        }
+
+        let tesselations = &self.tesselations;
+        let is_live = |id: usize| tesselations[id].is_some();
+        self.path_index.retain(|_, loaded| is_live(loaded.id));
+        self.aabb_index.retain(|_, id| is_live(*id));
+    }
+    pub fn stats(&self) -> AssetStats {
+        AssetStats {
+            live_tesselations: self.tesselations.iter().filter(|t| t.is_some()).count(),
+            cached_meshes: self.path_index.len(),
+            aabb_meshes: self.aabb_index.len(),
+            pending_loads: self.pending.len(),
+        }
     }
     pub fn init_shape(&mut self, shape: Shape) -> Shape {
         match shape {
             Shape::Init { .. } => shape,
-            Shape::Unit { obj_path } => match &self.path_index.get(&obj_path) {
-                Some(shape) => (*shape).clone(),
-                None => {
-                    let tesselation = ObjLoader::load(Path::new(&obj_path)).unwrap();
-                    let bounding_box = AABB {
-                        middle_point: tesselation.middle_point.clone(),
-                        half_size: Vector3::from([
-                            [tesselation.x_half_size],
-                            [tesselation.y_half_size],
-                            [tesselation.z_half_size],
-                        ]),
-                    };
-                    let shape_tess = tesselation
-                        .build_tess(&mut *self.surface.borrow_mut())
-                        .unwrap();
-                    self.tesselations.push(Some(shape_tess));
-                    let tess_id = self.tesselations.len() - 1;
-
-                    let shape = Shape::Init {
-                        tess_id,
-                        bounding_box,
-                        bounding_box_tess_id: None,
-                    };
-                    let shape_clone = shape.clone();
-                    self.path_index.insert(obj_path, shape);
-
-                    shape_clone
+            Shape::Unit { obj_path } | Shape::Loading { obj_path } => {
+                match self.path_index.get(&obj_path) {
+                    Some(loaded) => Shape::Init {
+                        tess: self.handle(loaded.id),
+                        bounding_box: loaded.bounding_box.clone(),
+                        obj_path,
+                        bounding_box_tess: None,
+                    },
+                    None => {
+                        self.request(&obj_path);
+                        Shape::Loading { obj_path }
+                    }
+                }
+            }
+        }
+    }
+    pub fn preload(&mut self, obj_paths: &[String]) {
+        for obj_path in obj_paths {
+            self.preloaded.insert(obj_path.clone());
+            self.request(obj_path);
+        }
+    }
+    fn request(&mut self, obj_path: &str) {
+        if self.path_index.contains_key(obj_path) || self.pending.contains(obj_path) {
+            return;
+        }
+        self.send_request(obj_path);
+    }
+    // Loads an already cached mesh again, with every level of detail made from it. The old
+    // versions are kept until the new ones are ready.
+    pub fn reload(&mut self, obj_path: &str) {
+        let paths: Vec<String> = self
+            .path_index
+            .keys()
+            .chain(self.pending.iter())
+            .filter(|path| ObjLoader::detail_source(path) == obj_path)
+            .cloned()
+            .collect();
+        for path in paths {
+            if self.pending.contains(&path) {
+                // The loader may have read the file before it changed
+                self.stale.insert(path);
+            } else {
+                self.reloading.insert(path.clone());
+                self.send_request(&path);
+            }
+        }
+    }
+    fn send_request(&mut self, obj_path: &str) {
+        self.pending.insert(obj_path.to_owned());
+        self.load_requests
+            .send(obj_path.to_owned())
+            .expect("The mesh loader threads have stopped");
+    }
+    // Uploads every mesh the loader threads have finished parsing since the last call,
+    // returns the obj paths of the meshes which were reloaded. With wait it blocks until every
+    // requested mesh is in, so a shape is ready at the same tick on every run.
+    pub fn poll_loaded(&mut self, wait: bool) -> Vec<String> {
+        let mut reloaded: Vec<String> = Vec::new();
+        loop {
+            let result = if wait && !self.pending.is_empty() {
+                self.load_results.recv().ok()
+            } else {
+                self.load_results.try_recv().ok()
+            };
+            let (obj_path, loaded) = match result {
+                Some(result) => result,
+                None => break,
+            };
+            self.pending.remove(&obj_path);
+            let is_reload = self.reloading.remove(&obj_path);
+            if self.stale.remove(&obj_path) {
+                self.reloading.insert(obj_path.clone());
+                self.send_request(&obj_path);
+            }
+            let loaded = match loaded.and_then(|tesselation| self.upload(tesselation)) {
+                Ok(loaded) => loaded,
+                Err(e) if is_reload => {
+                    error!(
+                        "Could not reload {}, keeping the old version: {}",
+                        obj_path, e
+                    );
+                    continue;
+                }
+                Err(e) => {
+                    error!(
+                        "Could not load {}, using the placeholder mesh instead: {}",
+                        obj_path, e
+                    );
+                    // Remember the failure, so we don't retry the load every frame
+                    self.placeholder()
                 }
-            },
+            };
+            if self.preloaded.contains(&obj_path) {
+                self.pinned.insert(loaded.id);
+            }
+            // The old version of a reloaded mesh goes once its shapes have let go of it
+            if let Some(old) = self.path_index.get(&obj_path) {
+                if !self.is_placeholder(old.id) {
+                    self.pinned.remove(&old.id);
+                }
+            }
+            self.path_index.insert(obj_path.clone(), loaded);
+            if is_reload {
+                reloaded.push(obj_path);
+            }
+        }
+        reloaded
+    }
+    // Meshes which could not be loaded are drawn with the placeholder
+    pub fn is_placeholder(&self, id: usize) -> bool {
+        match &self.placeholder {
+            Some(placeholder) => placeholder.id == id,
+            None => false,
         }
     }
+    fn placeholder(&mut self) -> Loaded {
+        if let Some(placeholder) = &self.placeholder {
+            return placeholder.clone();
+        }
+        let placeholder = self
+            .upload(ObjLoader::generate_placeholder())
+            .expect("The placeholder mesh could not be built");
+        self.pinned.insert(placeholder.id);
+        self.placeholder = Some(placeholder.clone());
+        placeholder
+    }
+    fn upload(&mut self, tesselation: ObjLoader) -> Result<Loaded, AssetError> {
+        let bounding_box = AABB {
+            middle_point: tesselation.middle_point.clone(),
+            half_size: Vector3::from([
+                [tesselation.x_half_size],
+                [tesselation.y_half_size],
+                [tesselation.z_half_size],
+            ]),
+        };
+        let id = self.insert_tess(tesselation)?;
+        Ok(Loaded { id, bounding_box })
+    }
 }
@@ -0,0 +1,307 @@
+use crate::ecs::components::damage::DamageType;
+use crate::ecs::components::lifetime::Lifetime;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const WEAPONS_PATH: &str = "weapons.cfg";
+pub const KEYS: [&str; 14] = [
+    "damage",
+    "damage-type",
+    "speed",
+    "fire-interval",
+    "magazine",
+    "reload-time",
+    "barrels",
+    "spread",
+    "projectile",
+    "projectile-scale",
+    "despawn-on-impact",
+    "homing",
+    "range",
+    "lifetime",
+];
+const DEFAULT_PROJECTILE: &str = "models/bullet.obj";
+
+#[derive(Debug)]
+pub enum WeaponError {
+    Io(String, io::Error),
+    Syntax { line: usize, text: String },
+    UnknownKey { weapon: String, key: String },
+    InvalidValue { key: String, value: String },
+    Duplicate { line: usize, name: String },
+    Unknown(String),
+}
+
+impl fmt::Display for WeaponError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeaponError::Io(path, e) => write!(f, "cannot read {}: {}", path, e),
+            WeaponError::Syntax { line, text } => write!(
+                f,
+                "line {} should look like `[name]` or `key = value` below one, found `{}`",
+                line, text
+            ),
+            WeaponError::UnknownKey { weapon, key } => write!(
+                f,
+                "unknown key `{}` in weapon `{}`, known keys are: {}",
+                key,
+                weapon,
+                KEYS.join(", ")
+            ),
+            WeaponError::InvalidValue { key, value } => {
+                write!(f, "`{}` is not a valid value for `{}`", value, key)
+            }
+            WeaponError::Duplicate { line, name } => {
+                write!(
+                    f,
+                    "line {} defines the weapon `{}` a second time",
+                    line, name
+                )
+            }
+            WeaponError::Unknown(name) => write!(f, "there is no weapon called `{}`", name),
+        }
+    }
+}
+
+impl std::error::Error for WeaponError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Weapon {
+    pub name: String,
+    pub damage: f32,
+    pub damage_type: DamageType,
+    // Along the nose, on top of the velocity of the shooter
+    pub speed: f32,
+    // Ticks between two shots
+    pub fire_interval: f32,
+    // Projectiles before it has to reload, 0 never runs out
+    pub magazine: u32,
+    // Ticks
+    pub reload_time: f32,
+    // Every shot fires one projectile per barrel, fanned out sideways
+    pub barrels: u32,
+    // The angle between two barrels in radians
+    pub spread: f32,
+    pub projectile: String,
+    pub projectile_scale: f32,
+    pub despawn_on_impact: bool,
+    // Radians per tick the projectile turns towards its target, 0 flies straight
+    pub homing: f32,
+    // How far the projectiles fly before they despawn, 0 has no limit
+    pub range: f32,
+    // Ticks before the projectiles despawn, 0 has no limit
+    pub lifetime: f32,
+}
+
+impl Weapon {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            damage: 10.0,
+            damage_type: DamageType::Kinetic,
+            speed: 5.0,
+            fire_interval: 10.0,
+            magazine: 0,
+            reload_time: 0.0,
+            barrels: 1,
+            spread: 0.0,
+            projectile: DEFAULT_PROJECTILE.to_owned(),
+            projectile_scale: 2.0,
+            despawn_on_impact: true,
+            homing: 0.0,
+            range: 2000.0,
+            lifetime: 0.0,
+        }
+    }
+    // The limits of the projectiles, None when they have none
+    pub fn lifetime(&self) -> Option<Lifetime> {
+        let limit = |value: f32| if value > 0.0 { Some(value) } else { None };
+        match (limit(self.lifetime), limit(self.range)) {
+            (None, None) => None,
+            (max_age, max_distance) => Some(Lifetime::new(max_age, max_distance)),
+        }
+    }
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), WeaponError> {
+        let invalid = || WeaponError::InvalidValue {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        };
+        let non_negative = || match value.parse::<f32>() {
+            Ok(number) if number >= 0.0 => Ok(number),
+            _ => Err(invalid()),
+        };
+        match key {
+            "damage" => self.damage = non_negative()?,
+            "damage-type" => self.damage_type = DamageType::parse(value).ok_or_else(invalid)?,
+            "speed" => self.speed = non_negative()?,
+            "fire-interval" => self.fire_interval = non_negative()?,
+            "magazine" => self.magazine = value.parse().map_err(|_| invalid())?,
+            "reload-time" => self.reload_time = non_negative()?,
+            "barrels" => match value.parse() {
+                Ok(barrels) if barrels > 0 => self.barrels = barrels,
+                _ => return Err(invalid()),
+            },
+            // In degrees in the file
+            "spread" => self.spread = non_negative()?.to_radians(),
+            "projectile" => self.projectile = value.to_owned(),
+            "projectile-scale" => self.projectile_scale = non_negative()?,
+            "despawn-on-impact" => match value {
+                "true" => self.despawn_on_impact = true,
+                "false" => self.despawn_on_impact = false,
+                _ => return Err(invalid()),
+            },
+            "homing" => self.homing = non_negative()?,
+            "range" => self.range = non_negative()?,
+            "lifetime" => self.lifetime = non_negative()?,
+            _ => {
+                return Err(WeaponError::UnknownKey {
+                    weapon: self.name.clone(),
+                    key: key.to_owned(),
+                })
+            }
+        }
+        Ok(())
+    }
+}
+
+// Every weapon in the order of the definition file
+#[derive(Default)]
+pub struct Weapons(pub Vec<Weapon>);
+
+impl Weapons {
+    pub fn read<P>(path: P) -> Result<String, WeaponError>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        fs::read_to_string(path).map_err(|e| WeaponError::Io(path.display().to_string(), e))
+    }
+    // A `[name]` line starts a weapon, the `key = value` lines below it change its defaults.
+    // Everything after a # is a comment.
+    pub fn parse(source: &str) -> Result<Self, WeaponError> {
+        let mut weapons: Vec<Weapon> = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            let text = line.split('#').next().unwrap_or("").trim();
+            if text.is_empty() {
+                continue;
+            }
+            let syntax = || WeaponError::Syntax {
+                line: i + 1,
+                text: text.to_owned(),
+            };
+            if text.starts_with('[') && text.ends_with(']') {
+                let name = text[1..text.len() - 1].trim();
+                if weapons.iter().any(|w| w.name == name) {
+                    return Err(WeaponError::Duplicate {
+                        line: i + 1,
+                        name: name.to_owned(),
+                    });
+                }
+                weapons.push(Weapon::new(name));
+                continue;
+            }
+            let mut parts = text.splitn(2, '=');
+            match (weapons.last_mut(), parts.next(), parts.next()) {
+                (Some(weapon), Some(key), Some(value)) => weapon.set(key.trim(), value.trim())?,
+                _ => return Err(syntax()),
+            }
+        }
+        Ok(Weapons(weapons))
+    }
+    pub fn get(&self, name: &str) -> Result<&Weapon, WeaponError> {
+        self.0
+            .iter()
+            .find(|w| w.name == name)
+            .ok_or_else(|| WeaponError::Unknown(name.to_owned()))
+    }
+    // The one after the given weapon, going back to the first after the last
+    pub fn next(&self, name: &str) -> Option<&Weapon> {
+        let index = self
+            .0
+            .iter()
+            .position(|w| w.name == name)
+            .map_or(0, |i| i + 1);
+        self.0.get(index % self.0.len().max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_weapons() {
+        let source = "# comment
+[cannon]
+damage = 20 # inline
+damage-type = energy
+
+[ scattergun ]
+barrels = 5
+spread = 90
+";
+        let weapons = Weapons::parse(source).unwrap();
+        assert_eq!(weapons.0.len(), 2);
+        let cannon = weapons.get("cannon").unwrap();
+        assert_eq!(cannon.damage, 20.0);
+        assert_eq!(cannon.damage_type, DamageType::Energy);
+        assert_eq!(cannon.speed, Weapon::new("default").speed);
+        let scattergun = weapons.get("scattergun").unwrap();
+        assert_eq!(scattergun.barrels, 5);
+        assert!((scattergun.spread - std::f32::consts::FRAC_PI_2).abs() < 0.0001);
+        assert_eq!(weapons.next("scattergun").unwrap().name, "cannon");
+    }
+
+    #[test]
+    fn shipped_weapons_are_valid() {
+        let weapons = Weapons::parse(&Weapons::read(WEAPONS_PATH).unwrap()).unwrap();
+        assert!(!weapons.0.is_empty());
+    }
+
+    #[test]
+    fn duplicate_weapons() {
+        match Weapons::parse("[cannon]\n[laser]\n[cannon]\n") {
+            Err(WeaponError::Duplicate { line: 3, name }) => assert_eq!(name, "cannon"),
+            _ => panic!("a second cannon should be rejected"),
+        }
+    }
+
+    #[test]
+    fn negative_values() {
+        for key in &[
+            "damage",
+            "speed",
+            "fire-interval",
+            "reload-time",
+            "spread",
+            "projectile-scale",
+            "homing",
+            "range",
+            "lifetime",
+        ] {
+            match Weapons::parse(&format!("[cannon]\n{} = -1\n", key)) {
+                Err(WeaponError::InvalidValue { key: k, .. }) => assert_eq!(k, *key),
+                _ => panic!("a negative {} should be rejected", key),
+            }
+            assert!(Weapons::parse(&format!("[cannon]\n{} = 0\n", key)).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_lines() {
+        match Weapons::parse("damage = 1\n") {
+            Err(WeaponError::Syntax { line: 1, .. }) => (),
+            _ => panic!("a key before any weapon should be rejected"),
+        }
+        match Weapons::parse("[cannon]\ncolour = red\n") {
+            Err(WeaponError::UnknownKey { .. }) => (),
+            _ => panic!("unknown keys should be rejected"),
+        }
+        match Weapons::parse("[cannon]\nbarrels = 0\n") {
+            Err(WeaponError::InvalidValue { .. }) => (),
+            _ => panic!("a weapon without barrels should be rejected"),
+        }
+    }
+}